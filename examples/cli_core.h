@@ -13,6 +13,78 @@ void cli_log_info(const char* message);
 void cli_log_warn(const char* message);
 void cli_log_error(const char* message);
 void cli_log_success(const char* message);
+void cli_log_debug(const char* message);
+void cli_log_trace(const char* message);
+// Logs, flushes pending output and exits the process with `code`
+void cli_log_fatal(const char* message, int code);
+
+// Level filter, e.g. "info" or "mytool::net=debug,info" (CLI_CORE_LOG uses the same syntax)
+bool cli_set_log_level(const char* spec);
+
+// Console line layout. Placeholders: {timestamp} or {timestamp:<strftime>}, {level},
+// {program}, {target}, {message}, {fields}. Returns false for an invalid format.
+bool cli_set_log_format(const char* format);
+void cli_set_program_name(const char* name);
+
+// Repeated messages: collapse identical consecutive lines, and allow at most
+// max_per_window identical messages per window (0 disables the limit).
+// Summaries are logged from a timer once a window closes; call
+// cli_flush_logs before exiting to log counts still pending.
+void cli_set_log_dedup(bool enabled);
+void cli_set_log_rate_limit(uint32_t max_per_window, uint64_t window_ms);
+
+// Redaction: registered secrets and regex matches are masked in messages and fields.
+// Passwords read with cli_read_password are registered automatically.
+void cli_register_secret(const char* secret);
+bool cli_add_redaction_pattern(const char* pattern);
+
+// Additional sinks (Unix only). NULL uses /dev/log and /run/systemd/journal/socket.
+bool cli_add_syslog_sink(const char* socket_path);
+bool cli_add_journald_sink(const char* socket_path);
+
+// Background logging. policy: 0 = block, 1 = drop, 2 = drop and report the count.
+// cli_shutdown_logging writes everything still queued; call it before exiting.
+// cli_enable_async_logging returns false for an unknown policy or if the thread can't start.
+bool cli_enable_async_logging(size_t capacity, int policy);
+void cli_flush_logs(void);
+void cli_shutdown_logging(void);
+uint64_t cli_dropped_log_count(void);
+
+// Log levels as used by the capture functions
+typedef enum {
+    CLI_LOG_DEBUG = 0,
+    CLI_LOG_INFO = 1,
+    CLI_LOG_SUCCESS = 2,
+    CLI_LOG_WARN = 3,
+    CLI_LOG_ERROR = 4,
+    CLI_LOG_TRACE = 5,
+    CLI_LOG_FATAL = 6,
+} CliLogLevel;
+
+// Capture the calling thread's logs in memory instead of printing them (for tests).
+// Strings returned by message/fields must be released with cli_free_string.
+void cli_capture_begin(void);
+void cli_capture_end(void);
+size_t cli_capture_count(void);
+void cli_capture_clear(void);
+int cli_capture_level(size_t index);
+char* cli_capture_message(size_t index);
+char* cli_capture_fields(size_t index);
+double cli_capture_timestamp(size_t index);
+bool cli_capture_contains(int level, const char* needle);
+
+// Scoped logging (per thread; end returns elapsed seconds, or -1 if no scope was open)
+void cli_scope_begin(const char* name);
+double cli_scope_end(void);
+
+// Color policy: honors NO_COLOR, CLICOLOR, CLICOLOR_FORCE, TERM=dumb and TTY detection in auto mode
+typedef enum {
+    CLI_COLOR_AUTO = 0,
+    CLI_COLOR_ALWAYS = 1,
+    CLI_COLOR_NEVER = 2,
+} CliColorMode;
+
+bool cli_set_color_mode(int mode);
 
 // Template functions
 char* cli_get_template(const char* key);
@@ -25,6 +97,111 @@ bool cli_load_config(const char* path);
 size_t cli_create_progress_bar(uint64_t total);
 bool cli_update_progress(size_t id, uint64_t current, const char* message);
 bool cli_finish_progress(size_t id, const char* message);
+// Stop a bar before completion; drawn differently from a finished one
+bool cli_abandon_progress(size_t id, const char* message);
+bool cli_cancel_progress(size_t id, const char* message);
+// Release a bar and its id. A bar still running is left on screen as abandoned.
+// Only the 64 most recent finished bars are kept if callers never free them.
+bool cli_free_progress(size_t id);
+// Continue a job that reached position in an earlier run lasting elapsed_ms
+bool cli_resume_progress_from(size_t id, uint64_t position, uint64_t elapsed_ms);
+// Hide a bar and stop its clock, e.g. around a prompt; paused time is left out of elapsed and ETA
+bool cli_pause_progress(size_t id);
+bool cli_resume_progress(size_t id);
+// The same for every bar at once; calls nest
+void cli_suspend_all_progress(void);
+void cli_resume_all_progress(void);
+// Positions are bytes, drawn as KiB/MiB/GiB with throughput per second
+size_t cli_create_bytes_progress_bar(uint64_t total);
+
+// Spinners for work of unknown size. cli_set_progress_length switches a spinner to a
+// regular bar; cli_enable_steady_tick redraws from a background thread (0 ms stops it).
+size_t cli_create_spinner(void);
+bool cli_set_progress_length(size_t id, uint64_t total);
+bool cli_enable_steady_tick(size_t id, uint64_t interval_ms);
+// Redraws are capped at hz per second (default 15, 0 = every update); the final state is always drawn
+bool cli_set_progress_draw_rate(size_t id, uint32_t hz);
+
+// How progress is shown. Auto draws live bars on a terminal and prints a plain status line
+// ("42% 420/1000 ETA 12s") every interval otherwise, e.g. in CI logs or pipes.
+typedef enum {
+    CLI_PROGRESS_AUTO = 0,
+    CLI_PROGRESS_LIVE = 1,
+    CLI_PROGRESS_PLAIN = 2,
+    CLI_PROGRESS_HIDDEN = 3,
+} CliProgressMode;
+
+bool cli_set_progress_mode(int mode);
+void cli_set_progress_plain_interval(uint64_t interval_ms);
+
+// Progress bar styles. Template placeholders: {bar} or {bar:<width>}, {spinner}, {icon},
+// {percent}, {pos}, {len}, {rate}, {eta}, {elapsed}, {msg}, {prefix}, {bytes}, {total_bytes},
+// {bytes_per_sec}.
+// chars: filled cell first, empty cell last, partial cells in between (e.g. "=> " or "█▉▊▋▌▍▎▏ ").
+// width 0 fills the terminal; color is a name like "green", NULL for none.
+bool cli_set_progress_template(size_t id, const char* template_str);
+bool cli_set_progress_chars(size_t id, const char* chars);
+bool cli_set_progress_width(size_t id, size_t width);
+bool cli_set_progress_color(size_t id, const char* color);
+bool cli_set_progress_prefix(size_t id, const char* prefix);
+
+// Where a bar is drawn; stderr by default so stdout stays free for data.
+// cli_set_progress_target_fd (Unix only) writes plain status lines to fd, which stays owned
+// by the caller; they are never colored.
+typedef enum {
+    CLI_PROGRESS_TARGET_STDERR = 0,
+    CLI_PROGRESS_TARGET_STDOUT = 1,
+    CLI_PROGRESS_TARGET_HIDDEN = 2,
+} CliProgressTarget;
+
+bool cli_set_progress_target(size_t id, int target);
+bool cli_set_progress_target_fd(size_t id, int fd);
+// Spinner look: frames is "dots", "line", "circle", "bounce", "arrow" or a string of frame characters
+bool cli_set_spinner_template(size_t id, const char* template_str);
+bool cli_set_spinner_frames(size_t id, const char* frames);
+
+// Hierarchical progress: the parent shows the weighted completion of its children plus the
+// line of the child currently running. cli_add_child_progress returns a bar id for
+// cli_update_progress / cli_finish_progress, or SIZE_MAX if parent_id is unknown.
+// Events and callbacks for the parent report current/total in thousandths (total is 1000).
+size_t cli_create_parent_progress(void);
+size_t cli_add_child_progress(size_t parent_id, double weight, uint64_t total);
+
+// Progress events as JSON lines for GUIs and editor integrations, e.g.
+// {"event":"updated","id":3,"pos":420,"total":1000,"rate":35.2,"eta":16.5,"elapsed":12.0,"message":""}
+// Events: created, updated, message, finished, abandoned, cancelled. "id" matches the bar id.
+// Terminal drawing continues; use cli_set_progress_mode(CLI_PROGRESS_HIDDEN) for events only.
+// The callback's json string is valid only during the call; it must not call progress functions.
+typedef void (*CliProgressEventCallback)(const char* json, void* user_data);
+// Unix only
+bool cli_progress_events_to_fd(int fd);
+bool cli_progress_events_to_callback(CliProgressEventCallback callback, void* user_data);
+void cli_clear_progress_event_sinks(void);
+
+// Custom rendering: the callback receives every draw of the bar instead of the terminal.
+// message is valid only during the call; the callback must not call progress functions
+// for the same bar. Passing NULL as callback goes back to terminal drawing.
+typedef struct {
+    uint64_t id;
+    uint64_t current;
+    uint64_t total;      // meaningless when has_total is false (spinners)
+    bool has_total;
+    double rate;         // items per second
+    double eta;          // seconds left, -1 when unknown
+    double elapsed;
+    int state;           // 0 = running, 1 = finished, 2 = abandoned, 3 = cancelled
+    const char* message;
+} CliProgressState;
+
+typedef void (*CliProgressCallback)(const CliProgressState* state, void* user_data);
+bool cli_set_progress_callback(size_t id, CliProgressCallback callback, void* user_data);
+
+// Multi-progress: bars added to one block are redrawn together on separate lines.
+// cli_multi_progress_add returns a bar id for cli_update_progress / cli_finish_progress,
+// or SIZE_MAX if multi_id is unknown.
+size_t cli_create_multi_progress(bool keep_finished);
+size_t cli_multi_progress_add(size_t multi_id, uint64_t total);
+bool cli_finish_multi_progress(size_t multi_id);
 
 // Command line argument parser
 typedef struct ArgParser ArgParser;
@@ -117,6 +117,7 @@ impl ArgParser {
 }
 
 // C interface
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[unsafe(no_mangle)]
 pub extern "C" fn cli_create_arg_parser(program_name: *const c_char) -> *mut ArgParser {
     let c_name = unsafe {
@@ -135,6 +136,7 @@ pub extern "C" fn cli_create_arg_parser(program_name: *const c_char) -> *mut Arg
     }
 }
 
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[unsafe(no_mangle)]
 pub extern "C" fn cli_set_parser_description(parser: *mut ArgParser, description: *const c_char) {
     if parser.is_null() || description.is_null() {
@@ -150,6 +152,7 @@ pub extern "C" fn cli_set_parser_description(parser: *mut ArgParser, description
     }
 }
 
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[unsafe(no_mangle)]
 pub extern "C" fn cli_parse_args(parser: *mut ArgParser, argc: i32, argv: *const *const c_char) -> bool {
     if parser.is_null() || argv.is_null() || argc <= 0 {
@@ -159,7 +162,7 @@ pub extern "C" fn cli_parse_args(parser: *mut ArgParser, argc: i32, argv: *const
     let mut args: Vec<String> = Vec::new();
     
     for i in 0..argc {
-        let arg_ptr = unsafe { *argv.offset(i as isize) };
+        let arg_ptr = unsafe { *argv.add(i as usize) };
         if arg_ptr.is_null() {
             continue;
         }
@@ -177,6 +180,7 @@ pub extern "C" fn cli_parse_args(parser: *mut ArgParser, argc: i32, argv: *const
     result.is_ok()
 }
 
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[unsafe(no_mangle)]
 pub extern "C" fn cli_arg_parser_get(parser: *const ArgParser, key: *const c_char) -> *mut c_char {
     if parser.is_null() || key.is_null() {
@@ -197,6 +201,7 @@ pub extern "C" fn cli_arg_parser_get(parser: *const ArgParser, key: *const c_cha
     std::ptr::null_mut()
 }
 
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[unsafe(no_mangle)]
 pub extern "C" fn cli_arg_parser_has_flag(parser: *const ArgParser, flag: *const c_char) -> bool {
     if parser.is_null() || flag.is_null() {
@@ -211,6 +216,7 @@ pub extern "C" fn cli_arg_parser_has_flag(parser: *const ArgParser, flag: *const
     }
 }
 
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[unsafe(no_mangle)]
 pub extern "C" fn cli_arg_parser_print_help(parser: *const ArgParser) {
     if !parser.is_null() {
@@ -220,6 +226,7 @@ pub extern "C" fn cli_arg_parser_print_help(parser: *const ArgParser) {
     }
 }
 
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[unsafe(no_mangle)]
 pub extern "C" fn cli_arg_parser_free(parser: *mut ArgParser) {
     if !parser.is_null() {
@@ -0,0 +1,241 @@
+use colored::{ColoredString, Styles};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::env;
+use std::io::{self, IsTerminal};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+// When colored output is produced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorChoice {
+    // Decide from the environment and whether the stream is a terminal
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+// Stream a piece of output is headed for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    pub fn is_terminal(&self) -> bool {
+        match self {
+            Stream::Stdout => io::stdout().is_terminal(),
+            Stream::Stderr => io::stderr().is_terminal(),
+        }
+    }
+}
+
+impl ColorChoice {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ColorChoice::Auto => "auto",
+            ColorChoice::Always => "always",
+            ColorChoice::Never => "never",
+        }
+    }
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            other => Err(format!("不明なカラー設定です: {}", other)),
+        }
+    }
+}
+
+impl Serialize for ColorChoice {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+// Accepts "auto" / "always" / "never", and the older boolean form (true = auto, false = never)
+impl<'de> Deserialize<'de> for ColorChoice {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Flag(bool),
+            Name(String),
+        }
+
+        match Repr::deserialize(deserializer)? {
+            Repr::Flag(true) => Ok(ColorChoice::Auto),
+            Repr::Flag(false) => Ok(ColorChoice::Never),
+            Repr::Name(name) => name.parse().map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+static COLOR_CHOICE: AtomicU8 = AtomicU8::new(0);
+
+pub fn set_color_choice(choice: ColorChoice) {
+    let value = match choice {
+        ColorChoice::Auto => 0,
+        ColorChoice::Always => 1,
+        ColorChoice::Never => 2,
+    };
+    COLOR_CHOICE.store(value, Ordering::Relaxed);
+}
+
+pub fn color_choice() -> ColorChoice {
+    match COLOR_CHOICE.load(Ordering::Relaxed) {
+        1 => ColorChoice::Always,
+        2 => ColorChoice::Never,
+        _ => ColorChoice::Auto,
+    }
+}
+
+// Whether output for `stream` should carry ANSI colors under the current policy
+pub fn should_colorize(stream: Stream) -> bool {
+    match color_choice() {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => colorize_from_env(stream),
+    }
+}
+
+fn colorize_from_env(stream: Stream) -> bool {
+    colorize_with(|key| env::var(key).ok(), stream.is_terminal())
+}
+
+// CLICOLOR_FORCE wins over NO_COLOR, which wins over CLICOLOR, TERM=dumb and the TTY check.
+// `var` looks up an environment variable, so the rules can be checked without touching the
+// process environment.
+fn colorize_with(var: impl Fn(&str) -> Option<String>, is_terminal: bool) -> bool {
+    if var("CLICOLOR_FORCE").is_some_and(|v| !v.is_empty() && v != "0") {
+        return true;
+    }
+    if var("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+        return false;
+    }
+    if var("CLICOLOR").is_some_and(|v| v == "0") {
+        return false;
+    }
+    interactive_with(var, is_terminal)
+}
+
+// Whether `stream` is a terminal that understands cursor movement, i.e. can
+// show output redrawn in place such as progress bars
+pub fn is_interactive(stream: Stream) -> bool {
+    interactive_with(|key| env::var(key).ok(), stream.is_terminal())
+}
+
+fn interactive_with(var: impl Fn(&str) -> Option<String>, is_terminal: bool) -> bool {
+    if var("TERM").is_some_and(|v| v == "dumb") {
+        return false;
+    }
+    is_terminal
+}
+
+// Render `styled` for `stream`, dropping the escapes when color is off
+pub fn paint(styled: ColoredString, stream: Stream) -> String {
+    paint_if(styled, should_colorize(stream))
+}
+
+// Render `styled` with or without escapes. The escapes are written here rather
+// than by `colored`, whose own decision is a process-wide switch shared with
+// the host application.
+pub fn paint_if(styled: ColoredString, enabled: bool) -> String {
+    let mut codes: Vec<String> = Vec::new();
+    if enabled {
+        const STYLE_CODES: [(Styles, &str); 8] = [
+            (Styles::Bold, "1"),
+            (Styles::Dimmed, "2"),
+            (Styles::Italic, "3"),
+            (Styles::Underline, "4"),
+            (Styles::Blink, "5"),
+            (Styles::Reversed, "7"),
+            (Styles::Hidden, "8"),
+            (Styles::Strikethrough, "9"),
+        ];
+        codes.extend(
+            STYLE_CODES
+                .iter()
+                .filter(|(style, _)| styled.style.contains(*style))
+                .map(|(_, code)| code.to_string()),
+        );
+        codes.extend(styled.bgcolor.map(|color| color.to_bg_str().into_owned()));
+        codes.extend(styled.fgcolor.map(|color| color.to_fg_str().into_owned()));
+    }
+
+    if codes.is_empty() {
+        styled.input
+    } else {
+        format!("\x1b[{}m{}\x1b[0m", codes.join(";"), styled.input)
+    }
+}
+
+// C interface
+
+// 0 = auto, 1 = always, 2 = never. Returns false for an unknown mode.
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_set_color_mode(mode: i32) -> bool {
+    let choice = match mode {
+        0 => ColorChoice::Auto,
+        1 => ColorChoice::Always,
+        2 => ColorChoice::Never,
+        _ => return false,
+    };
+    set_color_choice(choice);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn colorize(vars: &[(&str, &str)], is_terminal: bool) -> bool {
+        colorize_with(
+            |key| vars.iter().find(|(name, _)| *name == key).map(|(_, value)| value.to_string()),
+            is_terminal,
+        )
+    }
+
+    #[test]
+    fn terminals_are_colored_by_default() {
+        assert!(colorize(&[], true));
+        assert!(!colorize(&[], false));
+        assert!(colorize(&[("TERM", "xterm-256color"), ("CLICOLOR", "1")], true));
+    }
+
+    #[test]
+    fn clicolor_force_wins_over_everything() {
+        let all_off = [("NO_COLOR", "1"), ("CLICOLOR", "0"), ("TERM", "dumb")];
+        assert!(colorize(&[&all_off[..], &[("CLICOLOR_FORCE", "1")]].concat(), false));
+        // Empty or "0" doesn't force
+        assert!(!colorize(&[("CLICOLOR_FORCE", "0")], false));
+        assert!(!colorize(&[("CLICOLOR_FORCE", "")], false));
+    }
+
+    #[test]
+    fn no_color_wins_over_clicolor_and_the_terminal() {
+        assert!(!colorize(&[("NO_COLOR", "1"), ("CLICOLOR", "1")], true));
+        assert!(!colorize(&[("NO_COLOR", "anything")], true));
+        assert!(colorize(&[("NO_COLOR", "")], true));
+    }
+
+    #[test]
+    fn clicolor_zero_turns_color_off() {
+        assert!(!colorize(&[("CLICOLOR", "0")], true));
+        assert!(colorize(&[("CLICOLOR", "yes")], true));
+    }
+
+    #[test]
+    fn dumb_terminals_get_no_color() {
+        assert!(!colorize(&[("TERM", "dumb")], true));
+        assert!(!interactive_with(|key| (key == "TERM").then(|| "dumb".to_string()), true));
+        assert!(interactive_with(|_| None, true));
+    }
+}
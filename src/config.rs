@@ -1,19 +1,115 @@
 use std::fs;
 use std::path::Path;
+use std::time::Duration;
 use serde::{Deserialize, Serialize};
+use crate::color::{self, ColorChoice};
 use crate::errors::CliError;
+use crate::logger::{self, FileSink, Rotation};
+use crate::progress::{self, ProgressMode};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Config {
     pub logger: LoggerConfig,
     pub templates: TemplatesConfig,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_file: Option<LogFileConfig>,
+    #[serde(default)]
+    pub progress: ProgressConfig,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProgressConfig {
+    // auto / live / plain / hidden
+    #[serde(default)]
+    pub mode: ProgressMode,
+    // Seconds between status lines when progress is printed as plain lines
+    #[serde(default = "default_plain_interval_secs")]
+    pub plain_interval_secs: f64,
+}
+
+impl Default for ProgressConfig {
+    fn default() -> Self {
+        ProgressConfig {
+            mode: ProgressMode::Auto,
+            plain_interval_secs: default_plain_interval_secs(),
+        }
+    }
+}
+
+fn default_plain_interval_secs() -> f64 {
+    progress::DEFAULT_PLAIN_INTERVAL.as_secs_f64()
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct LoggerConfig {
+    // `info` or per target, e.g. `mytool::net=debug,info`; CLI_CORE_LOG takes precedence
     pub level: String,
-    pub color: bool,
+    pub color: ColorChoice,
     pub timestamp: bool,
+    // Console line layout, see `logger::LogFormat`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    // Value for `{program}` in the format
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub program: Option<String>,
+    // Collapse identical consecutive messages
+    #[serde(default)]
+    pub dedup: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimitConfig>,
+    // Mask the built-in credential patterns (bearer tokens, password=...)
+    #[serde(default)]
+    pub redact_defaults: bool,
+    // Extra regular expressions to mask; only capture groups are masked if present
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redact_patterns: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    // Identical messages allowed per window, 0 turns the limit off
+    pub max_per_window: u32,
+    pub window_secs: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RotationKind {
+    #[default]
+    Never,
+    Size,
+    Daily,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LogFileConfig {
+    pub path: String,
+    #[serde(default)]
+    pub rotation: RotationKind,
+    #[serde(default = "default_max_size")]
+    pub max_size: u64,
+    #[serde(default = "default_max_files")]
+    pub max_files: usize,
+    #[serde(default)]
+    pub compress: bool,
+}
+
+fn default_max_size() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_max_files() -> usize {
+    5
+}
+
+impl LogFileConfig {
+    pub fn rotation(&self) -> Rotation {
+        match self.rotation {
+            RotationKind::Never => Rotation::Never,
+            RotationKind::Size => Rotation::Size(self.max_size),
+            RotationKind::Daily => Rotation::Daily,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -27,12 +123,20 @@ impl Default for Config {
         Config {
             logger: LoggerConfig {
                 level: "info".to_string(),
-                color: true,
+                color: ColorChoice::Auto,
                 timestamp: true,
+                format: None,
+                program: None,
+                dedup: false,
+                rate_limit: None,
+                redact_defaults: false,
+                redact_patterns: Vec::new(),
             },
             templates: TemplatesConfig {
                 custom_templates: std::collections::HashMap::new(),
             },
+            log_file: None,
+            progress: ProgressConfig::default(),
         }
     }
 }
@@ -51,4 +155,115 @@ impl Config {
         fs::write(path, content)?;
         Ok(())
     }
-}
\ No newline at end of file
+
+    // Install the color policy, line format and sinks described by this config into the
+    // global logger, along with how progress is shown
+    pub fn apply(&self) -> Result<(), CliError> {
+        let rate_limit = match &self.logger.rate_limit {
+            Some(limit) if limit.max_per_window > 0 => {
+                Some((limit.max_per_window, secs_to_duration(limit.window_secs, "logger.rate_limit.window_secs")?))
+            }
+            _ => None,
+        };
+        let plain_interval = secs_to_duration(self.progress.plain_interval_secs, "progress.plain_interval_secs")?;
+
+        color::set_color_choice(self.logger.color);
+
+        if logger::filter_from_env().is_none() {
+            logger::set_level_filter(&self.logger.level)?;
+        }
+
+        match &self.logger.format {
+            Some(format) => logger::set_log_format(format)?,
+            None if !self.logger.timestamp => logger::set_log_format("[{level}] {message} {fields}")?,
+            None => logger::set_log_format(logger::DEFAULT_FORMAT)?,
+        }
+        if let Some(program) = &self.logger.program {
+            logger::set_program_name(program);
+        }
+
+        logger::set_config_redactions(self.logger.redact_defaults, &self.logger.redact_patterns)?;
+
+        logger::set_dedup(self.logger.dedup);
+        logger::set_rate_limit(rate_limit);
+
+        progress::set_progress_mode(self.progress.mode);
+        progress::set_plain_interval(plain_interval);
+
+        // Replaces the sink of a previous apply, so reloading doesn't open the file twice
+        let sink = match &self.log_file {
+            Some(log_file) => Some(
+                FileSink::new(&log_file.path)?
+                    .with_rotation(log_file.rotation())
+                    .with_max_files(log_file.max_files)
+                    .with_compression(log_file.compress),
+            ),
+            None => None,
+        };
+        logger::set_config_sink(sink.map(|sink| Box::new(sink) as Box<dyn logger::LogSink>));
+        Ok(())
+    }
+}
+
+// Seconds from the config file, which may be any float TOML accepts
+fn secs_to_duration(secs: f64, key: &str) -> Result<Duration, CliError> {
+    Duration::try_from_secs_f64(secs)
+        .map_err(|_| crate::errors::config_error(format!("{} の秒数が不正です: {}", key, secs)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reapplying_replaces_the_log_file_sink() {
+        let _lock = logger::SINK_TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = std::env::temp_dir().join(format!("cli_core_config_{}", std::process::id()));
+        let path = dir.join("app.log");
+        let config = Config {
+            log_file: Some(LogFileConfig {
+                path: path.to_string_lossy().into_owned(),
+                rotation: RotationKind::Never,
+                max_size: default_max_size(),
+                max_files: default_max_files(),
+                compress: false,
+            }),
+            ..Config::default()
+        };
+
+        config.apply().unwrap();
+        config.apply().unwrap();
+        logger::log_info("written once after reload");
+        logger::flush_logs();
+        logger::set_config_sink(None);
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content.matches("written once after reload").count(), 1);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn out_of_range_seconds_are_config_errors() {
+        assert_eq!(secs_to_duration(1.5, "key").unwrap(), Duration::from_millis(1500));
+        for secs in [1e20, f64::INFINITY, f64::NAN, -1.0] {
+            assert!(secs_to_duration(secs, "key").is_err());
+        }
+
+        let config: Config = toml::from_str(
+            "[logger]\nlevel = \"info\"\ncolor = \"auto\"\ntimestamp = true\n\
+             [logger.rate_limit]\nmax_per_window = 3\nwindow_secs = 1e20\n[templates]\n",
+        )
+        .unwrap();
+        assert!(config.apply().is_err());
+    }
+
+    #[test]
+    fn infinite_plain_interval_is_a_config_error() {
+        let config: Config = toml::from_str(
+            "[logger]\nlevel = \"info\"\ncolor = \"auto\"\ntimestamp = true\n[templates]\n\
+             [progress]\nplain_interval_secs = inf\n",
+        )
+        .unwrap();
+        assert!(config.apply().is_err());
+    }
+}
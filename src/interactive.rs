@@ -3,8 +3,15 @@ use colored::*;
 use std::ffi::{CStr, CString};
 use std::os::raw::c_char;
 
+use std::time::Duration;
+
+use crate::color::{Stream, paint};
+use crate::progress::{self, ProgressBar, ProgressStyle};
+
 pub fn prompt(message: &str) -> Result<String, io::Error> {
-    print!("{} ", message.bright_cyan());
+    // Keep progress bars off the line being typed on until the answer is in
+    let _progress = progress::suspend_all();
+    print!("{} ", paint(message.bright_cyan(), Stream::Stdout));
     stdout().flush()?;
     
     let mut input = String::new();
@@ -14,6 +21,7 @@ pub fn prompt(message: &str) -> Result<String, io::Error> {
 }
 
 pub fn confirm(message: &str, default: bool) -> Result<bool, io::Error> {
+    let _progress = progress::suspend_all();
     let prompt_message = if default {
         format!("{} [Y/n]: ", message)
     } else {
@@ -30,21 +38,22 @@ pub fn confirm(message: &str, default: bool) -> Result<bool, io::Error> {
         "y" | "yes" | "はい" => Ok(true),
         "n" | "no" | "いいえ" => Ok(false),
         _ => {
-            println!("{} Enter y or n", "⚠️".yellow());
+            println!("{} Enter y or n", paint("⚠️".yellow(), Stream::Stdout));
             confirm(message, default)
         }
     }
 }
 
 pub fn select_option(message: &str, options: &[&str]) -> Result<usize, io::Error> {
+    let _progress = progress::suspend_all();
     if options.is_empty() {
         return Err(io::Error::new(io::ErrorKind::InvalidInput, "選択肢が存在しません。"));
     }
     
-    println!("{}", message.bright_cyan());
+    println!("{}", paint(message.bright_cyan(), Stream::Stdout));
     
     for (i, option) in options.iter().enumerate() {
-        println!("  {}. {}", (i + 1).to_string().green(), option);
+        println!("  {}. {}", paint((i + 1).to_string().green(), Stream::Stdout), option);
     }
     
     loop {
@@ -56,50 +65,62 @@ pub fn select_option(message: &str, options: &[&str]) -> Result<usize, io::Error
             }
         }
         
-        println!("{}有効な数字をいれてください。(1-{})", "⚠️ ".yellow(), options.len());
+        println!("{}有効な数字をいれてください。(1-{})", paint("⚠️ ".yellow(), Stream::Stdout), options.len());
     }
 }
 
 // password
 pub fn read_password(prompt_message: &str) -> Result<String, io::Error> {
-    print!("{} ", prompt_message.bright_cyan());
+    let _progress = progress::suspend_all();
+    print!("{} ", paint(prompt_message.bright_cyan(), Stream::Stdout));
     stdout().flush()?;
     
     let password = rpassword::read_password()?;
     println!();
+
+    // Keep the password out of any log output from here on
+    crate::logger::register_password(&password);
     
     Ok(password)
 }
 
 pub fn read_multiline(prompt_message: &str) -> Result<String, io::Error> {
-    println!("{} (入力が終了したらCtrl+Dを押してください)", prompt_message.bright_cyan());
-    println!("{}", "---------- 入力開始 ----------".bright_black());
+    let _progress = progress::suspend_all();
+    println!("{} (入力が終了したらCtrl+Dを押してください)", paint(prompt_message.bright_cyan(), Stream::Stdout));
+    println!("{}", paint("---------- 入力開始 ----------".bright_black(), Stream::Stdout));
     
     let mut result = String::new();
     let stdin = io::stdin();
     stdin.lock().read_to_string(&mut result)?;
     
-    println!("{}", "---------- 入力終了 ----------".bright_black());
+    println!("{}", paint("---------- 入力終了 ----------".bright_black(), Stream::Stdout));
     
     Ok(result)
 }
 
 pub fn animated_wait(message: &str, seconds: u64) -> Result<(), io::Error> {
-    print!("{}", message);
-    stdout().flush()?;
-    
-    for _ in 0..seconds*2 {
-        print!(".");
-        stdout().flush()?;
-        std::thread::sleep(std::time::Duration::from_millis(500));
+    let spinner = ProgressBar::new_spinner();
+    if let Ok(mut bar) = spinner.lock() {
+        if let Ok(style) = ProgressStyle::new()
+            .with_spinner_template("{spinner} {msg}")
+            .and_then(|style| style.with_plain_spinner_template("{msg} {elapsed}")) {
+            bar.set_style(style);
+        }
+        bar.set_message(message);
+    }
+    ProgressBar::enable_steady_tick(&spinner, Duration::from_millis(80));
+
+    std::thread::sleep(Duration::from_secs(seconds));
+
+    if let Ok(mut bar) = spinner.lock() {
+        bar.finish_with_message(&format!("{} 完了", message));
     }
-    
-    println!("完了");
     Ok(())
 }
 
 // C interface
 
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[unsafe(no_mangle)]
 pub extern "C" fn cli_prompt(message: *const c_char) -> *mut c_char {
     let c_msg = unsafe {
@@ -125,6 +146,7 @@ pub extern "C" fn cli_prompt(message: *const c_char) -> *mut c_char {
     }
 }
 
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[unsafe(no_mangle)]
 pub extern "C" fn cli_confirm(message: *const c_char, default: bool) -> bool {
     let c_msg = unsafe {
@@ -145,6 +167,7 @@ pub extern "C" fn cli_confirm(message: *const c_char, default: bool) -> bool {
     }
 }
 
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[unsafe(no_mangle)]
 pub extern "C" fn cli_select_option(message: *const c_char, 
                                    options: *const *const c_char,
@@ -162,7 +185,7 @@ pub extern "C" fn cli_select_option(message: *const c_char,
     let mut rust_options: Vec<&str> = Vec::with_capacity(options_count);
     
     for i in 0..options_count {
-        let opt_ptr = unsafe { *options.offset(i as isize) };
+        let opt_ptr = unsafe { *options.add(i) };
         if opt_ptr.is_null() {
             continue;
         }
@@ -179,6 +202,7 @@ pub extern "C" fn cli_select_option(message: *const c_char,
     }
 }
 
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[unsafe(no_mangle)]
 pub extern "C" fn cli_read_password(prompt_message: *const c_char) -> *mut c_char {
     let c_msg = unsafe {
@@ -5,20 +5,25 @@ pub mod config;
 pub mod progress;
 pub mod args;
 pub mod interactive;
+pub mod color;
+mod placeholder;
+mod term;
 
 pub const VERSION: &str = "0.1.0";
 pub const NAME: &str = "cli_core";
 
-pub use logger::{log_info, log_warn, log_error, log_success, log_debug};
+pub use logger::{log_info, log_warn, log_error, log_success, log_debug, log_trace, log_fatal, set_level_filter, add_sink, FileSink, Rotation, scope, log_with, set_log_format, set_program_name, LogLevel};
 pub use templates::{get_template, add_template, remove_template};
 pub use errors::{CliError, config_error, auth_error, network_error, unknown_error};
 pub use config::Config;
-pub use progress::{create_progress_bar, update_progress, finish_progress};
+pub use color::{ColorChoice, set_color_choice};
+pub use progress::{create_progress_bar, create_spinner, update_progress, finish_progress, free_progress, MultiProgress, ProgressIterator, ProgressState, ProgressStyle, SpinnerFrames};
 
 // C FFI
 use std::ffi::{CStr, CString};
 use std::os::raw::c_char;
 
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[unsafe(no_mangle)]
 pub extern "C" fn cli_log_info(message: *const c_char) {
     let c_str = unsafe {
@@ -33,6 +38,7 @@ pub extern "C" fn cli_log_info(message: *const c_char) {
     }
 }
 
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[unsafe(no_mangle)]
 pub extern "C" fn cli_log_warn(message: *const c_char) {
     let c_str = unsafe {
@@ -47,6 +53,7 @@ pub extern "C" fn cli_log_warn(message: *const c_char) {
     }
 }
 
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[unsafe(no_mangle)]
 pub extern "C" fn cli_log_error(message: *const c_char) {
     let c_str = unsafe {
@@ -61,6 +68,7 @@ pub extern "C" fn cli_log_error(message: *const c_char) {
     }
 }
 
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[unsafe(no_mangle)]
 pub extern "C" fn cli_log_success(message: *const c_char) {
     let c_str = unsafe {
@@ -75,6 +83,97 @@ pub extern "C" fn cli_log_success(message: *const c_char) {
     }
 }
 
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_log_debug(message: *const c_char) {
+    let c_str = unsafe {
+        if message.is_null() {
+            return;
+        }
+        CStr::from_ptr(message)
+    };
+
+    if let Ok(message_str) = c_str.to_str() {
+        log_debug(message_str);
+    }
+}
+
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_log_trace(message: *const c_char) {
+    let c_str = unsafe {
+        if message.is_null() {
+            return;
+        }
+        CStr::from_ptr(message)
+    };
+
+    if let Ok(message_str) = c_str.to_str() {
+        log_trace(message_str);
+    }
+}
+
+// Logs the message, flushes pending output and exits with `code`
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_log_fatal(message: *const c_char, code: i32) {
+    let message_str = if message.is_null() {
+        ""
+    } else {
+        unsafe { CStr::from_ptr(message) }.to_str().unwrap_or("")
+    };
+
+    log_fatal(message_str, code)
+}
+
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_set_log_level(spec: *const c_char) -> bool {
+    let c_str = unsafe {
+        if spec.is_null() {
+            return false;
+        }
+        CStr::from_ptr(spec)
+    };
+
+    match c_str.to_str() {
+        Ok(spec_str) => set_level_filter(spec_str).is_ok(),
+        Err(_) => false,
+    }
+}
+
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_set_log_format(format: *const c_char) -> bool {
+    let c_str = unsafe {
+        if format.is_null() {
+            return false;
+        }
+        CStr::from_ptr(format)
+    };
+
+    match c_str.to_str() {
+        Ok(format_str) => logger::set_log_format(format_str).is_ok(),
+        Err(_) => false,
+    }
+}
+
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_set_program_name(name: *const c_char) {
+    let c_str = unsafe {
+        if name.is_null() {
+            return;
+        }
+        CStr::from_ptr(name)
+    };
+
+    if let Ok(name_str) = c_str.to_str() {
+        logger::set_program_name(name_str);
+    }
+}
+
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[unsafe(no_mangle)]
 pub extern "C" fn cli_get_template(key: *const c_char) -> *mut c_char {
     let c_str = unsafe {
@@ -99,6 +198,7 @@ pub extern "C" fn cli_get_template(key: *const c_char) -> *mut c_char {
     }
 }
 
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[unsafe(no_mangle)]
 pub extern "C" fn cli_free_string(ptr: *mut c_char) {
     unsafe {
@@ -108,6 +208,7 @@ pub extern "C" fn cli_free_string(ptr: *mut c_char) {
     }
 }
 
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[unsafe(no_mangle)]
 pub extern "C" fn cli_load_config(path: *const c_char) -> bool {
     let c_str = unsafe {
@@ -120,9 +221,7 @@ pub extern "C" fn cli_load_config(path: *const c_char) -> bool {
     match c_str.to_str() {
         Ok(path_str) => {
             match config::Config::load(path_str) {
-                Ok(_loaded_config) => {
-                    true
-                },
+                Ok(loaded_config) => loaded_config.apply().is_ok(),
                 Err(_) => false,
             }
         },
@@ -135,30 +234,285 @@ pub extern "C" fn cli_create_progress_bar(total: u64) -> usize {
     progress::create_progress_bar(total)
 }
 
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[unsafe(no_mangle)]
 pub extern "C" fn cli_update_progress(id: usize, current: u64, message: *const c_char) -> bool {
     let message_str = if message.is_null() {
         None
     } else {
-        match unsafe { CStr::from_ptr(message) }.to_str() {
-            Ok(s) => Some(s),
-            Err(_) => None,
-        }
+        unsafe { CStr::from_ptr(message) }.to_str().ok()
     };
 
     progress::update_progress(id, current, message_str)
 }
 
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[unsafe(no_mangle)]
 pub extern "C" fn cli_finish_progress(id: usize, message: *const c_char) -> bool {
     let message_str = if message.is_null() {
         None
     } else {
-        match unsafe { CStr::from_ptr(message) }.to_str() {
-            Ok(s) => Some(s),
-            Err(_) => None,
-        }
+        unsafe { CStr::from_ptr(message) }.to_str().ok()
     };
 
     progress::finish_progress(id, message_str)
-}
\ No newline at end of file
+}
+
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_abandon_progress(id: usize, message: *const c_char) -> bool {
+    let message_str = if message.is_null() {
+        None
+    } else {
+        unsafe { CStr::from_ptr(message) }.to_str().ok()
+    };
+
+    progress::abandon_progress(id, message_str)
+}
+
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_cancel_progress(id: usize, message: *const c_char) -> bool {
+    let message_str = if message.is_null() {
+        None
+    } else {
+        unsafe { CStr::from_ptr(message) }.to_str().ok()
+    };
+
+    progress::cancel_progress(id, message_str)
+}
+
+// Release a bar; the id is invalid afterwards. A bar still running is left on screen as abandoned.
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_free_progress(id: usize) -> bool {
+    progress::free_progress(id)
+}
+
+// Continue a job that reached `position` in an earlier run lasting `elapsed_ms`,
+// so elapsed time and ETA carry on from there
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_resume_progress_from(id: usize, position: u64, elapsed_ms: u64) -> bool {
+    progress::resume_progress_from(id, position, std::time::Duration::from_millis(elapsed_ms))
+}
+
+// Hide the bar and stop its clock until cli_resume_progress
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_pause_progress(id: usize) -> bool {
+    progress::pause_progress(id)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_resume_progress(id: usize) -> bool {
+    progress::resume_progress(id)
+}
+
+// A bar whose position and total are byte counts, shown as KiB/MiB/GiB with MiB/s throughput
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_create_bytes_progress_bar(total: u64) -> usize {
+    progress::create_bytes_progress_bar(total)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_create_spinner() -> usize {
+    progress::create_spinner()
+}
+
+// Give a spinner its total so it is drawn as a regular bar from now on
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_set_progress_length(id: usize, total: u64) -> bool {
+    progress::set_progress_length(id, total)
+}
+
+// 0 = auto (live bars on a terminal, plain lines otherwise), 1 = live, 2 = plain, 3 = hidden.
+// Returns false for an unknown mode.
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_set_progress_mode(mode: i32) -> bool {
+    let mode = match mode {
+        0 => progress::ProgressMode::Auto,
+        1 => progress::ProgressMode::Live,
+        2 => progress::ProgressMode::Plain,
+        3 => progress::ProgressMode::Hidden,
+        _ => return false,
+    };
+    progress::set_progress_mode(mode);
+    true
+}
+
+// Time between status lines when progress is printed as plain lines
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_set_progress_plain_interval(interval_ms: u64) {
+    progress::set_plain_interval(std::time::Duration::from_millis(interval_ms));
+}
+
+// Draw the bar at most `hz` times a second (default 15); 0 draws on every update
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_set_progress_draw_rate(id: usize, hz: u32) -> bool {
+    progress::set_progress_draw_rate(id, hz)
+}
+
+// Redraw the bar from a background thread every `interval_ms`; 0 stops it
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_enable_steady_tick(id: usize, interval_ms: u64) -> bool {
+    progress::set_progress_steady_tick(id, std::time::Duration::from_millis(interval_ms))
+}
+
+// An overall bar whose progress is the weighted completion of its sub-tasks
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_create_parent_progress() -> usize {
+    progress::create_parent_progress()
+}
+
+// Add a sub-task counting to `total` with a relative `weight` (e.g. 60, 30, 10).
+// Returns the id of the new bar for use with cli_update_progress, or SIZE_MAX if the parent does not exist
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_add_child_progress(parent_id: usize, weight: f64, total: u64) -> usize {
+    progress::add_child_progress(parent_id, weight, total).unwrap_or(usize::MAX)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_create_multi_progress(keep_finished: bool) -> usize {
+    progress::create_multi_progress(keep_finished)
+}
+
+// Returns the id of the new bar for use with cli_update_progress, or SIZE_MAX if the multi-progress does not exist
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_multi_progress_add(multi_id: usize, total: u64) -> usize {
+    progress::add_to_multi_progress(multi_id, total).unwrap_or(usize::MAX)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_finish_multi_progress(multi_id: usize) -> bool {
+    progress::finish_multi_progress(multi_id)
+}
+
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_set_progress_template(id: usize, template: *const c_char) -> bool {
+    let c_str = unsafe {
+        if template.is_null() {
+            return false;
+        }
+        CStr::from_ptr(template)
+    };
+
+    match c_str.to_str() {
+        Ok(template_str) => progress::restyle_progress(id, |style| style.with_template(template_str)),
+        Err(_) => false,
+    }
+}
+
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_set_progress_chars(id: usize, chars: *const c_char) -> bool {
+    let c_str = unsafe {
+        if chars.is_null() {
+            return false;
+        }
+        CStr::from_ptr(chars)
+    };
+
+    match c_str.to_str() {
+        Ok(chars_str) => progress::restyle_progress(id, |style| style.with_chars(chars_str)),
+        Err(_) => false,
+    }
+}
+
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_set_spinner_template(id: usize, template: *const c_char) -> bool {
+    let c_str = unsafe {
+        if template.is_null() {
+            return false;
+        }
+        CStr::from_ptr(template)
+    };
+
+    match c_str.to_str() {
+        Ok(template_str) => progress::restyle_progress(id, |style| style.with_spinner_template(template_str)),
+        Err(_) => false,
+    }
+}
+
+// A built-in animation name ("dots", "line", "circle", "bounce", "arrow"),
+// or a string whose characters are used as the frames
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_set_spinner_frames(id: usize, frames: *const c_char) -> bool {
+    let c_str = unsafe {
+        if frames.is_null() {
+            return false;
+        }
+        CStr::from_ptr(frames)
+    };
+
+    match c_str.to_str() {
+        Ok(frames_str) => match progress::SpinnerFrames::from_name(frames_str) {
+            Some(preset) => progress::restyle_progress(id, |style| Ok(style.with_spinner(preset))),
+            None => {
+                let frames: Vec<String> = frames_str.chars().map(String::from).collect();
+                let frames: Vec<&str> = frames.iter().map(String::as_str).collect();
+                progress::restyle_progress(id, |style| style.with_spinner_frames(&frames))
+            }
+        },
+        Err(_) => false,
+    }
+}
+
+// A width of 0 makes the bar fill the rest of the terminal line
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_set_progress_width(id: usize, width: usize) -> bool {
+    let width = if width == 0 { None } else { Some(width) };
+    progress::restyle_progress(id, |style| Ok(style.with_width(width)))
+}
+
+// Color name such as "green" or "bright blue"; NULL removes the color
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_set_progress_color(id: usize, color: *const c_char) -> bool {
+    if color.is_null() {
+        return progress::restyle_progress(id, |style| Ok(style.with_bar_color(None)));
+    }
+
+    match unsafe { CStr::from_ptr(color) }.to_str().ok().and_then(progress::parse_color) {
+        Some(color) => progress::restyle_progress(id, |style| Ok(style.with_bar_color(Some(color)))),
+        None => false,
+    }
+}
+
+// 0 = stderr (default), 1 = stdout, 2 = hidden. Returns false for an unknown target.
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_set_progress_target(id: usize, target: i32) -> bool {
+    let target = match target {
+        0 => progress::ProgressTarget::Stderr,
+        1 => progress::ProgressTarget::Stdout,
+        2 => progress::ProgressTarget::Hidden,
+        _ => return false,
+    };
+    progress::set_progress_target(id, target)
+}
+
+// Write plain status lines to the open file descriptor `fd`, which stays owned by the caller
+#[cfg(unix)]
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_set_progress_target_fd(id: usize, fd: i32) -> bool {
+    if fd < 0 {
+        return false;
+    }
+    progress::set_progress_target_fd(id, fd)
+}
+
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_set_progress_prefix(id: usize, prefix: *const c_char) -> bool {
+    let c_str = unsafe {
+        if prefix.is_null() {
+            return false;
+        }
+        CStr::from_ptr(prefix)
+    };
+
+    match c_str.to_str() {
+        Ok(prefix_str) => progress::set_progress_prefix(id, prefix_str),
+        Err(_) => false,
+    }
+}
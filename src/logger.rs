@@ -1,32 +1,329 @@
-use chrono::Local;
-use colored::*;
-use std::io::{self, Write};
+use chrono::{DateTime, Local};
+use lazy_static::lazy_static;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Mutex, RwLock};
 
-#[derive(Debug, Clone, Copy)]
+use crate::color::Stream;
+use crate::errors::CliError;
+use crate::term;
+
+mod background;
+mod capture;
+mod file;
+mod filter;
+mod format;
+mod redact;
+mod scope;
+#[cfg(unix)]
+mod syslog;
+mod throttle;
+
+pub use background::{AsyncLogGuard, OverflowPolicy, enable_async_logging, is_async_logging, dropped_log_count, flush_logs, shutdown_logging};
+pub use capture::{LogCapture, capture_logs};
+pub use file::{FileSink, Rotation};
+pub use filter::{LevelFilter, LOG_ENV_VAR};
+pub use format::{LogFormat, DEFAULT_FORMAT};
+#[cfg(unix)]
+pub use syslog::{SyslogSink, JournaldSink, syslog_severity, SYSLOG_SOCKET, JOURNALD_SOCKET};
+pub use throttle::{set_dedup, set_rate_limit};
+pub(crate) use redact::set_config_redactions;
+pub use redact::{register_secret, register_password, add_redaction_pattern, add_default_redaction_patterns, clear_redactions, redact, DEFAULT_REDACTION_PATTERNS, REDACTED};
+pub use scope::{ScopeGuard, ScopeStyle, scope, begin_scope, end_scope, current_scope, set_scope_style};
+
+// Ordered from least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum LogLevel {
+    Trace,
     Debug,
     Info,
     Success,
     Warning,
     Error,
+    Fatal,
 }
 
-fn log(level: LogLevel, message: &str) {
-    let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
-    
-    let prefix = match level {
-        LogLevel::Debug => "[DEBUG]".bright_black(),
-        LogLevel::Info => "[INFO]".bright_blue(),
-        LogLevel::Success => "[SUCCESS]".bright_green(),
-        LogLevel::Warning => "[WARN]".bright_yellow(),
-        LogLevel::Error => "[ERROR]".bright_red(),
+impl LogLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogLevel::Trace => "TRACE",
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Info => "INFO",
+            LogLevel::Success => "SUCCESS",
+            LogLevel::Warning => "WARN",
+            LogLevel::Error => "ERROR",
+            LogLevel::Fatal => "FATAL",
+        }
+    }
+
+    // Numbering used by the C interface
+    pub fn as_c_int(&self) -> i32 {
+        match self {
+            LogLevel::Debug => 0,
+            LogLevel::Info => 1,
+            LogLevel::Success => 2,
+            LogLevel::Warning => 3,
+            LogLevel::Error => 4,
+            LogLevel::Trace => 5,
+            LogLevel::Fatal => 6,
+        }
+    }
+
+    pub fn from_c_int(value: i32) -> Option<Self> {
+        match value {
+            0 => Some(LogLevel::Debug),
+            1 => Some(LogLevel::Info),
+            2 => Some(LogLevel::Success),
+            3 => Some(LogLevel::Warning),
+            4 => Some(LogLevel::Error),
+            5 => Some(LogLevel::Trace),
+            6 => Some(LogLevel::Fatal),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "trace" => Ok(LogLevel::Trace),
+            "debug" => Ok(LogLevel::Debug),
+            "info" => Ok(LogLevel::Info),
+            "success" => Ok(LogLevel::Success),
+            "warn" | "warning" => Ok(LogLevel::Warning),
+            "error" => Ok(LogLevel::Error),
+            "fatal" => Ok(LogLevel::Fatal),
+            other => Err(format!("不明なログレベルです: {}", other)),
+        }
+    }
+}
+
+// A single log entry as handed to sinks
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub level: LogLevel,
+    pub timestamp: DateTime<Local>,
+    // Subsystem the message came from, e.g. `mytool::net`
+    pub target: Option<String>,
+    pub message: String,
+    // Structured key/value pairs attached to the message
+    pub fields: Vec<(String, String)>,
+    // Open scopes at the time of logging, outermost first
+    pub scope: Vec<String>,
+}
+
+impl Record {
+    // Stamped with the current time and the calling thread's scopes
+    pub fn new(level: LogLevel, target: Option<&str>, message: &str, fields: &[(&str, &str)]) -> Self {
+        Record {
+            level,
+            timestamp: Local::now(),
+            target: target.map(|t| t.to_string()),
+            message: message.to_string(),
+            fields: fields.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            scope: current_scope(),
+        }
+    }
+
+    // `deploy > build > compile`, empty outside any scope
+    pub fn scope_path(&self) -> String {
+        self.scope.join(" > ")
+    }
+
+    // `key=value key2=value2`
+    pub fn fields_string(&self) -> String {
+        self.fields
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    // Uncolored single-line form used by file-like sinks
+    pub fn to_plain_line(&self) -> String {
+        let mut line = format!(
+            "{} [{}] ",
+            self.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            self.level.as_str()
+        );
+        if !self.scope.is_empty() {
+            line.push_str(&format!("[{}] ", self.scope_path()));
+        }
+        if let Some(target) = &self.target {
+            line.push_str(&format!("{}: ", target));
+        }
+        line.push_str(&self.message);
+        if !self.fields.is_empty() {
+            line.push(' ');
+            line.push_str(&self.fields_string());
+        }
+        line
+    }
+}
+
+// Destination for log records in addition to the console
+pub trait LogSink: Send {
+    fn write(&mut self, record: &Record) -> io::Result<()>;
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+lazy_static! {
+    static ref SINKS: Mutex<Vec<Box<dyn LogSink>>> = Mutex::new(Vec::new());
+    // The sink installed by `Config::apply`, replaced rather than added to on reload
+    static ref CONFIG_SINK: Mutex<Option<Box<dyn LogSink>>> = Mutex::new(None);
+    static ref FORMAT: RwLock<LogFormat> = RwLock::new(LogFormat::default());
+    static ref PROGRAM_NAME: RwLock<String> = RwLock::new(default_program_name());
+    static ref FILTER: RwLock<LevelFilter> = RwLock::new(filter_from_env().unwrap_or_default());
+}
+
+// Held by tests whose records must reach the sinks, so a test that stalls or
+// replaces the background logger can't drop them
+#[cfg(test)]
+pub(crate) static SINK_TEST_LOCK: Mutex<()> = Mutex::new(());
+
+pub fn add_sink<S: LogSink + 'static>(sink: S) {
+    if let Ok(mut sinks) = SINKS.lock() {
+        sinks.push(Box::new(sink));
+    }
+}
+
+pub fn clear_sinks() {
+    if let Ok(mut sinks) = SINKS.lock() {
+        for sink in sinks.iter_mut() {
+            let _ = sink.flush();
+        }
+        sinks.clear();
+    }
+    set_config_sink(None);
+}
+
+pub(crate) fn set_config_sink(sink: Option<Box<dyn LogSink>>) {
+    if let Ok(mut current) = CONFIG_SINK.lock() {
+        if let Some(old) = current.as_mut() {
+            let _ = old.flush();
+        }
+        *current = sink;
+    }
+}
+
+// Change the console line layout, see `LogFormat` for the placeholders
+pub fn set_log_format(template: &str) -> Result<(), CliError> {
+    let format = LogFormat::parse(template)?;
+    if let Ok(mut current) = FORMAT.write() {
+        *current = format;
+    }
+    Ok(())
+}
+
+// Replace the level filter, see `LevelFilter` for the syntax
+pub fn set_level_filter(spec: &str) -> Result<(), CliError> {
+    let filter = LevelFilter::parse(spec)?;
+    if let Ok(mut current) = FILTER.write() {
+        *current = filter;
+    }
+    Ok(())
+}
+
+// Filter from the CLI_CORE_LOG environment variable, if set and valid
+pub fn filter_from_env() -> Option<LevelFilter> {
+    std::env::var(LOG_ENV_VAR).ok().and_then(|spec| LevelFilter::parse(&spec).ok())
+}
+
+pub fn level_enabled(level: LogLevel, target: Option<&str>) -> bool {
+    FILTER.read().map(|filter| filter.enabled(level, target)).unwrap_or(true)
+}
+
+// Name substituted for `{program}`, defaults to the executable name
+pub fn set_program_name(name: &str) {
+    if let Ok(mut program) = PROGRAM_NAME.write() {
+        *program = name.to_string();
+    }
+}
+
+pub fn program_name() -> String {
+    PROGRAM_NAME.read().map(|program| program.clone()).unwrap_or_default()
+}
+
+fn default_program_name() -> String {
+    std::env::args()
+        .next()
+        .as_deref()
+        .map(Path::new)
+        .and_then(|path| path.file_stem())
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+pub fn log_with(level: LogLevel, target: Option<&str>, message: &str, fields: &[(&str, &str)]) {
+    if !level_enabled(level, target) {
+        return;
+    }
+
+    let mut record = Record::new(level, target, message, fields);
+    redact::redact_record(&mut record);
+    for record in throttle::admit(record) {
+        emit(record);
+    }
+}
+
+fn emit(record: Record) {
+    let Some(record) = capture::intercept(record) else {
+        return;
+    };
+    if let Some(record) = background::submit(record) {
+        dispatch(&record);
+    }
+}
+
+// Emit summaries held back by deduplication and rate limiting
+fn emit_throttled() {
+    for record in throttle::drain() {
+        emit(record);
+    }
+}
+
+// Render a record to the console and hand it to every sink
+fn dispatch(record: &Record) {
+    let log_message = match (FORMAT.read(), PROGRAM_NAME.read()) {
+        (Ok(format), Ok(program)) => format.render(record, &program, Stream::Stdout),
+        _ => record.to_plain_line(),
     };
-    
-    let log_message = format!("{} {} {}", timestamp.bright_black(), prefix, message);
-    
-    let mut stdout = io::stdout();
-    let _ = writeln!(stdout, "{}", log_message);
-    let _ = stdout.flush();
+
+    term::print_line(&log_message);
+
+    if let Ok(mut sinks) = SINKS.lock() {
+        for sink in sinks.iter_mut() {
+            let _ = sink.write(record);
+        }
+    }
+    if let Ok(mut sink) = CONFIG_SINK.lock() {
+        if let Some(sink) = sink.as_mut() {
+            let _ = sink.write(record);
+        }
+    }
+}
+
+fn flush_sinks() {
+    if let Ok(mut sinks) = SINKS.lock() {
+        for sink in sinks.iter_mut() {
+            let _ = sink.flush();
+        }
+    }
+    if let Ok(mut sink) = CONFIG_SINK.lock() {
+        if let Some(sink) = sink.as_mut() {
+            let _ = sink.flush();
+        }
+    }
+}
+
+fn log(level: LogLevel, message: &str) {
+    log_with(level, None, message, &[]);
 }
 
 pub fn log_info(message: &str) {
@@ -47,4 +344,15 @@ pub fn log_error(message: &str) {
 
 pub fn log_debug(message: &str) {
     log(LogLevel::Debug, message);
-}
\ No newline at end of file
+}
+
+pub fn log_trace(message: &str) {
+    log(LogLevel::Trace, message);
+}
+
+// Log at Fatal level, write out everything pending and exit the process with `code`
+pub fn log_fatal(message: &str, code: i32) -> ! {
+    log(LogLevel::Fatal, message);
+    shutdown_logging();
+    std::process::exit(code);
+}
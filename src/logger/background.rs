@@ -0,0 +1,298 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Mutex, RwLock};
+use std::thread::{self, JoinHandle};
+
+use super::{LogLevel, Record, dispatch, emit_throttled, flush_sinks};
+use crate::errors::CliError;
+
+// What to do when the queue of the background logger is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    // Wait for room; nothing is lost
+    Block,
+    // Discard the record silently
+    Drop,
+    // Discard the record and log how many were lost once there is room again
+    DropWithCounter,
+}
+
+enum Message {
+    Record(Box<Record>),
+    Flush(SyncSender<()>),
+}
+
+struct Backend {
+    sender: SyncSender<Message>,
+    policy: OverflowPolicy,
+}
+
+static BACKEND: RwLock<Option<Backend>> = RwLock::new(None);
+static WORKER: Mutex<Option<JoinHandle<()>>> = Mutex::new(None);
+static DROPPED_TOTAL: AtomicU64 = AtomicU64::new(0);
+static DROPPED_UNREPORTED: AtomicU64 = AtomicU64::new(0);
+
+// Shuts the background logger down when dropped
+#[must_use = "dropping the guard immediately stops background logging"]
+pub struct AsyncLogGuard {
+    _private: (),
+}
+
+impl Drop for AsyncLogGuard {
+    fn drop(&mut self) {
+        shutdown_logging();
+    }
+}
+
+// Move formatting and output of log records to a background thread with a queue of
+// `capacity` records. Replaces any background logger that is already running; if
+// the thread can't be started, logging stays synchronous.
+pub fn enable_async_logging(capacity: usize, policy: OverflowPolicy) -> Result<AsyncLogGuard, CliError> {
+    shutdown_logging();
+
+    let (sender, receiver) = mpsc::sync_channel(capacity.max(1));
+    let handle = thread::Builder::new()
+        .name("cli_core-logger".to_string())
+        .spawn(move || run_worker(receiver))?;
+
+    if let Ok(mut worker) = WORKER.lock() {
+        *worker = Some(handle);
+    }
+    if let Ok(mut backend) = BACKEND.write() {
+        *backend = Some(Backend { sender, policy });
+    }
+
+    Ok(AsyncLogGuard { _private: () })
+}
+
+pub fn is_async_logging() -> bool {
+    BACKEND.read().map(|backend| backend.is_some()).unwrap_or(false)
+}
+
+// Records discarded because the queue was full, since the process started
+pub fn dropped_log_count() -> u64 {
+    DROPPED_TOTAL.load(Ordering::Relaxed)
+}
+
+// Hand the record to the background thread. Gives it back if background logging is off.
+pub(super) fn submit(record: Record) -> Option<Record> {
+    let Ok(backend) = BACKEND.read() else {
+        return Some(record);
+    };
+    let Some(backend) = backend.as_ref() else {
+        return Some(record);
+    };
+
+    let message = Message::Record(Box::new(record));
+    match backend.policy {
+        OverflowPolicy::Block => {
+            if let Err(mpsc::SendError(Message::Record(record))) = backend.sender.send(message) {
+                return Some(*record);
+            }
+        }
+        OverflowPolicy::Drop | OverflowPolicy::DropWithCounter => match backend.sender.try_send(message) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                DROPPED_TOTAL.fetch_add(1, Ordering::Relaxed);
+                if backend.policy == OverflowPolicy::DropWithCounter {
+                    DROPPED_UNREPORTED.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            Err(TrySendError::Disconnected(Message::Record(record))) => return Some(*record),
+            Err(TrySendError::Disconnected(_)) => {}
+        },
+    }
+    None
+}
+
+// Wait until everything queued so far has been written, then flush all sinks
+pub fn flush_logs() {
+    emit_throttled();
+
+    let ack = match BACKEND.read() {
+        Ok(backend) => backend.as_ref().and_then(|backend| {
+            let (ack_sender, ack_receiver) = mpsc::sync_channel(1);
+            backend.sender.send(Message::Flush(ack_sender)).ok().map(|_| ack_receiver)
+        }),
+        Err(_) => None,
+    };
+
+    match ack {
+        Some(receiver) => {
+            let _ = receiver.recv();
+        }
+        None => flush_sinks(),
+    }
+}
+
+// Stop the background thread after it has written every queued record.
+// Logging continues synchronously afterwards.
+pub fn shutdown_logging() {
+    emit_throttled();
+
+    if let Ok(mut backend) = BACKEND.write() {
+        // Dropping the sender lets the worker drain the queue and exit
+        backend.take();
+    }
+    let handle = WORKER.lock().ok().and_then(|mut worker| worker.take());
+    if let Some(handle) = handle {
+        let _ = handle.join();
+    }
+    flush_sinks();
+}
+
+fn run_worker(receiver: Receiver<Message>) {
+    for message in receiver {
+        match message {
+            Message::Record(record) => {
+                dispatch(&record);
+                report_dropped();
+            }
+            Message::Flush(ack) => {
+                report_dropped();
+                flush_sinks();
+                let _ = ack.send(());
+            }
+        }
+    }
+    report_dropped();
+}
+
+fn report_dropped() {
+    let dropped = DROPPED_UNREPORTED.swap(0, Ordering::Relaxed);
+    if dropped > 0 {
+        let record = Record::new(
+            LogLevel::Warning,
+            None,
+            &format!("キューが満杯のため {} 件のログを破棄しました", dropped),
+            &[],
+        );
+        dispatch(&record);
+    }
+}
+
+// C interface
+
+// policy: 0 = block, 1 = drop, 2 = drop with counter. Call cli_shutdown_logging before exit.
+// Returns false for an unknown policy or if the logger thread can't be started.
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_enable_async_logging(capacity: usize, policy: i32) -> bool {
+    let policy = match policy {
+        0 => OverflowPolicy::Block,
+        1 => OverflowPolicy::Drop,
+        2 => OverflowPolicy::DropWithCounter,
+        _ => return false,
+    };
+    match enable_async_logging(capacity, policy) {
+        Ok(guard) => {
+            std::mem::forget(guard);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_flush_logs() {
+    flush_logs();
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_shutdown_logging() {
+    shutdown_logging();
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_dropped_log_count() -> u64 {
+    dropped_log_count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logger::{LogSink, add_sink, log_info};
+    use std::io;
+    use std::sync::{Arc, Condvar};
+
+    // Collects the records of one test, identified by a message prefix. While
+    // `held` is set the worker waits inside `write`, so the queue fills up.
+    #[derive(Clone, Default)]
+    struct Recorder {
+        prefix: &'static str,
+        seen: Arc<Mutex<Vec<String>>>,
+        held: Arc<(Mutex<bool>, Condvar)>,
+    }
+
+    impl Recorder {
+        fn new(prefix: &'static str) -> Self {
+            let recorder = Recorder { prefix, ..Default::default() };
+            add_sink(recorder.clone());
+            recorder
+        }
+
+        fn hold(&self, held: bool) {
+            *self.held.0.lock().unwrap() = held;
+            self.held.1.notify_all();
+        }
+
+        fn seen(&self) -> Vec<String> {
+            self.seen.lock().unwrap().clone()
+        }
+    }
+
+    impl LogSink for Recorder {
+        fn write(&mut self, record: &Record) -> io::Result<()> {
+            if record.message.starts_with(self.prefix) || record.message.contains("件のログを破棄しました") {
+                let (held, released) = &*self.held;
+                let _held = released.wait_while(held.lock().unwrap(), |held| *held).unwrap();
+                self.seen.lock().unwrap().push(record.message.clone());
+            }
+            Ok(())
+        }
+    }
+
+    // There is one background logger per process, so the policies are tested in turn
+    #[test]
+    fn overflow_policies_and_shutdown() {
+        let _lock = crate::logger::SINK_TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        // Block: nothing is lost and shutdown writes everything still queued
+        let recorder = Recorder::new("block ");
+        let guard = enable_async_logging(1, OverflowPolicy::Block).unwrap();
+        assert!(is_async_logging());
+        for i in 0..50 {
+            log_info(&format!("block {}", i));
+        }
+        drop(guard);
+        assert!(!is_async_logging());
+        let expected: Vec<String> = (0..50).map(|i| format!("block {}", i)).collect();
+        assert_eq!(recorder.seen(), expected);
+
+        // Drop: with the worker stuck, at most the record being written and one queued survive
+        let recorder = Recorder::new("drop ");
+        let before = dropped_log_count();
+        let guard = enable_async_logging(1, OverflowPolicy::Drop).unwrap();
+        recorder.hold(true);
+        for i in 0..10 {
+            log_info(&format!("drop {}", i));
+        }
+        recorder.hold(false);
+        drop(guard);
+        let kept = recorder.seen();
+        assert!((1..=2).contains(&kept.len()), "kept {:?}", kept);
+        assert!(dropped_log_count() - before >= 10 - kept.len() as u64);
+        assert_eq!(kept[0], "drop 0");
+
+        // DropWithCounter: the loss is reported once there is room again
+        let recorder = Recorder::new("count ");
+        let guard = enable_async_logging(1, OverflowPolicy::DropWithCounter).unwrap();
+        recorder.hold(true);
+        for i in 0..10 {
+            log_info(&format!("count {}", i));
+        }
+        recorder.hold(false);
+        drop(guard);
+        let seen = recorder.seen();
+        assert!(seen.iter().any(|message| message.contains("件のログを破棄しました")), "seen {:?}", seen);
+    }
+}
@@ -0,0 +1,292 @@
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::rc::Rc;
+
+use super::{LogLevel, Record};
+
+type Buffer = Rc<RefCell<Vec<Record>>>;
+
+thread_local! {
+    // Innermost capture last; only the innermost one receives records
+    static CAPTURES: RefCell<Vec<Buffer>> = const { RefCell::new(Vec::new()) };
+    static C_CAPTURE: RefCell<Option<LogCapture>> = const { RefCell::new(None) };
+}
+
+// Collects the records logged on the current thread instead of printing them.
+//
+// Captures are per thread, so tests running in parallel only see their own logs.
+// Records are captured before any background logger, so they are never dropped.
+// Dedup and rate-limit summaries logged by the throttle timer thread are not
+// captured; they show up here only when a later message or `flush_logs` on this
+// thread emits them.
+pub struct LogCapture {
+    buffer: Buffer,
+}
+
+// Start capturing the current thread's logs until the returned guard is dropped
+pub fn capture_logs() -> LogCapture {
+    let buffer: Buffer = Rc::new(RefCell::new(Vec::new()));
+    CAPTURES.with(|captures| captures.borrow_mut().push(buffer.clone()));
+    LogCapture { buffer }
+}
+
+// Store the record if a capture is active on this thread, otherwise give it back
+pub(super) fn intercept(record: Record) -> Option<Record> {
+    CAPTURES.with(|captures| match captures.borrow().last() {
+        Some(buffer) => {
+            buffer.borrow_mut().push(record);
+            None
+        }
+        None => Some(record),
+    })
+}
+
+impl LogCapture {
+    pub fn records(&self) -> Vec<Record> {
+        self.buffer.borrow().clone()
+    }
+
+    pub fn messages(&self) -> Vec<String> {
+        self.buffer.borrow().iter().map(|record| record.message.clone()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffer.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.borrow().is_empty()
+    }
+
+    pub fn clear(&self) {
+        self.buffer.borrow_mut().clear();
+    }
+
+    pub fn count(&self, level: LogLevel) -> usize {
+        self.buffer.borrow().iter().filter(|record| record.level == level).count()
+    }
+
+    // Whether a record with `level` has a message containing `needle`
+    pub fn contains(&self, level: LogLevel, needle: &str) -> bool {
+        self.buffer
+            .borrow()
+            .iter()
+            .any(|record| record.level == level && record.message.contains(needle))
+    }
+
+    // Whether any record has the field `key` set to `value`
+    pub fn contains_field(&self, key: &str, value: &str) -> bool {
+        self.buffer
+            .borrow()
+            .iter()
+            .any(|record| record.fields.iter().any(|(k, v)| k == key && v == value))
+    }
+
+    pub fn assert_logged(&self, level: LogLevel, needle: &str) {
+        if !self.contains(level, needle) {
+            panic!(
+                "expected a {} record containing {:?}, captured:\n{}",
+                level.as_str(),
+                needle,
+                self.dump()
+            );
+        }
+    }
+
+    pub fn assert_not_logged(&self, level: LogLevel, needle: &str) {
+        if self.contains(level, needle) {
+            panic!(
+                "unexpected {} record containing {:?}, captured:\n{}",
+                level.as_str(),
+                needle,
+                self.dump()
+            );
+        }
+    }
+
+    fn dump(&self) -> String {
+        self.buffer
+            .borrow()
+            .iter()
+            .map(|record| format!("  {}", record.to_plain_line()))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl Drop for LogCapture {
+    fn drop(&mut self) {
+        CAPTURES.with(|captures| {
+            captures.borrow_mut().retain(|buffer| !Rc::ptr_eq(buffer, &self.buffer));
+        });
+    }
+}
+
+// C interface
+//
+// One capture per thread: cli_capture_begin starts it (discarding a previous one),
+// cli_capture_end stops it. Levels use the same numbers as LogLevel::as_c_int.
+
+fn with_c_capture<T>(f: impl FnOnce(&LogCapture) -> T) -> Option<T> {
+    C_CAPTURE.with(|capture| capture.borrow().as_ref().map(f))
+}
+
+fn record_string(index: usize, f: impl FnOnce(&Record) -> String) -> *mut c_char {
+    let value = with_c_capture(|capture| capture.buffer.borrow().get(index).map(f)).flatten();
+    match value.map(CString::new) {
+        Some(Ok(c_value)) => c_value.into_raw(),
+        _ => std::ptr::null_mut(),
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_capture_begin() {
+    C_CAPTURE.with(|capture| {
+        let previous = capture.borrow_mut().take();
+        drop(previous);
+        *capture.borrow_mut() = Some(capture_logs());
+    });
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_capture_end() {
+    C_CAPTURE.with(|capture| {
+        let previous = capture.borrow_mut().take();
+        drop(previous);
+    });
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_capture_count() -> usize {
+    with_c_capture(|capture| capture.len()).unwrap_or(0)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_capture_clear() {
+    with_c_capture(|capture| capture.clear());
+}
+
+// Returns -1 when the index is out of range
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_capture_level(index: usize) -> i32 {
+    with_c_capture(|capture| capture.buffer.borrow().get(index).map(|record| record.level.as_c_int()))
+        .flatten()
+        .unwrap_or(-1)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_capture_message(index: usize) -> *mut c_char {
+    record_string(index, |record| record.message.clone())
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_capture_fields(index: usize) -> *mut c_char {
+    record_string(index, |record| record.fields_string())
+}
+
+// Seconds since the Unix epoch, or a negative value when the index is out of range
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_capture_timestamp(index: usize) -> f64 {
+    with_c_capture(|capture| {
+        capture
+            .buffer
+            .borrow()
+            .get(index)
+            .map(|record| record.timestamp.timestamp_millis() as f64 / 1000.0)
+    })
+    .flatten()
+    .unwrap_or(-1.0)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_capture_contains(level: i32, needle: *const c_char) -> bool {
+    let c_needle = unsafe {
+        if needle.is_null() {
+            return false;
+        }
+        CStr::from_ptr(needle)
+    };
+
+    match (LogLevel::from_c_int(level), c_needle.to_str()) {
+        (Some(level), Ok(needle_str)) => with_c_capture(|capture| capture.contains(level, needle_str)).unwrap_or(false),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logger::{log_error, log_info, log_warn, log_with};
+
+    #[test]
+    fn captures_records_of_the_current_thread() {
+        let capture = capture_logs();
+        log_warn("disk almost full");
+        log_with(LogLevel::Info, Some("net"), "connected", &[("host", "example.org")]);
+
+        assert_eq!(capture.len(), 2);
+        assert_eq!(capture.count(LogLevel::Warning), 1);
+        assert_eq!(capture.messages(), vec!["disk almost full", "connected"]);
+        assert_eq!(capture.records()[1].target.as_deref(), Some("net"));
+        assert!(capture.contains_field("host", "example.org"));
+        capture.assert_logged(LogLevel::Warning, "almost full");
+        capture.assert_not_logged(LogLevel::Error, "almost full");
+
+        capture.clear();
+        assert!(capture.is_empty());
+    }
+
+    #[test]
+    fn other_threads_are_not_captured() {
+        let capture = capture_logs();
+        std::thread::spawn(|| {
+            let inner = capture_logs();
+            log_info("from a worker");
+            assert_eq!(inner.len(), 1);
+        })
+        .join()
+        .unwrap();
+        assert!(capture.is_empty());
+    }
+
+    #[test]
+    fn only_the_innermost_capture_receives_records() {
+        let outer = capture_logs();
+        {
+            let inner = capture_logs();
+            log_error("inner");
+            assert_eq!(inner.len(), 1);
+        }
+        log_error("outer");
+        assert_eq!(outer.messages(), vec!["outer"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected a")]
+    fn assert_logged_panics_when_missing() {
+        let capture = capture_logs();
+        log_info("something else");
+        capture.assert_logged(LogLevel::Error, "missing");
+    }
+
+    #[test]
+    fn c_capture_reads_back_records() {
+        cli_capture_begin();
+        log_with(LogLevel::Warning, None, "from C", &[("code", "7")]);
+        assert_eq!(cli_capture_count(), 1);
+        assert_eq!(cli_capture_level(0), LogLevel::Warning.as_c_int());
+        assert_eq!(cli_capture_level(1), -1);
+        assert!(cli_capture_timestamp(0) > 0.0);
+        assert!(cli_capture_message(1).is_null());
+
+        let message = cli_capture_message(0);
+        assert_eq!(unsafe { CStr::from_ptr(message) }.to_str(), Ok("from C"));
+        crate::cli_free_string(message);
+
+        let needle = CString::new("from").unwrap();
+        assert!(cli_capture_contains(LogLevel::Warning.as_c_int(), needle.as_ptr()));
+        cli_capture_end();
+        assert_eq!(cli_capture_count(), 0);
+    }
+}
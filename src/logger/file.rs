@@ -0,0 +1,287 @@
+use chrono::{DateTime, Local, NaiveDate};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use super::{LogSink, Record};
+use crate::errors::CliError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    Never,
+    // Rotate once the current file would exceed this many bytes
+    Size(u64),
+    // Rotate on the first record of a new local day
+    Daily,
+}
+
+// Log file that rotates into `<path>.1`, `<path>.2`, ... (`.gz` when compressed)
+pub struct FileSink {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    rotation: Rotation,
+    max_files: usize,
+    compress: bool,
+    written: u64,
+    opened_on: NaiveDate,
+}
+
+impl FileSink {
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, CliError> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+
+        let file = open_append(&path)?;
+        let metadata = file.metadata()?;
+        let opened_on = metadata
+            .modified()
+            .map(|time| DateTime::<Local>::from(time).date_naive())
+            .unwrap_or_else(|_| Local::now().date_naive());
+
+        Ok(FileSink {
+            path,
+            writer: BufWriter::new(file),
+            rotation: Rotation::Never,
+            max_files: 5,
+            compress: false,
+            written: metadata.len(),
+            opened_on,
+        })
+    }
+
+    pub fn with_rotation(mut self, rotation: Rotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    // Number of rotated files kept next to the active one
+    pub fn with_max_files(mut self, max_files: usize) -> Self {
+        self.max_files = max_files;
+        self
+    }
+
+    // Gzip files as they are rotated out
+    pub fn with_compression(mut self, compress: bool) -> Self {
+        self.compress = compress;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn needs_rotation(&self, record: &Record, incoming: u64) -> bool {
+        match self.rotation {
+            Rotation::Never => false,
+            Rotation::Size(max_size) => self.written > 0 && self.written + incoming > max_size,
+            Rotation::Daily => record.timestamp.date_naive() != self.opened_on,
+        }
+    }
+
+    fn rotated_path(&self, index: usize, compressed: bool) -> PathBuf {
+        let mut name = self.path.as_os_str().to_os_string();
+        name.push(format!(".{}", index));
+        if compressed {
+            name.push(".gz");
+        }
+        PathBuf::from(name)
+    }
+
+    // Rotated files next to the active one as (index, compressed), highest index
+    // first. Listing the directory keeps a large `max_files` from costing a
+    // lookup per possible index.
+    fn rotated_files(&self) -> io::Result<Vec<(usize, bool)>> {
+        let Some(name) = self.path.file_name().and_then(|name| name.to_str()) else {
+            return Ok(Vec::new());
+        };
+        let dir = match self.path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+
+        let mut files = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let entry_name = entry?.file_name();
+            let Some(suffix) = entry_name.to_str().and_then(|entry_name| entry_name.strip_prefix(name)) else {
+                continue;
+            };
+            let Some(suffix) = suffix.strip_prefix('.') else {
+                continue;
+            };
+            let (index, compressed) = match suffix.strip_suffix(".gz") {
+                Some(index) => (index, true),
+                None => (suffix, false),
+            };
+            if let Ok(index) = index.parse::<usize>() {
+                if index > 0 {
+                    files.push((index, compressed));
+                }
+            }
+        }
+        files.sort_unstable_by(|a, b| b.cmp(a));
+        Ok(files)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.writer.flush()?;
+
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            // Highest index first, so each rename lands on a free name
+            for (index, compressed) in self.rotated_files()? {
+                let from = self.rotated_path(index, compressed);
+                if index >= self.max_files {
+                    fs::remove_file(from)?;
+                } else {
+                    fs::rename(from, self.rotated_path(index + 1, compressed))?;
+                }
+            }
+
+            let first = self.rotated_path(1, false);
+            fs::rename(&self.path, &first)?;
+            if self.compress {
+                compress_file(&first, &self.rotated_path(1, true))?;
+            }
+        }
+
+        self.writer = BufWriter::new(open_append(&self.path)?);
+        self.written = 0;
+        self.opened_on = Local::now().date_naive();
+        Ok(())
+    }
+}
+
+impl LogSink for FileSink {
+    fn write(&mut self, record: &Record) -> io::Result<()> {
+        let line = record.to_plain_line();
+        let incoming = line.len() as u64 + 1;
+
+        if self.needs_rotation(record, incoming) {
+            self.rotate()?;
+        }
+
+        writeln!(self.writer, "{}", line)?;
+        self.writer.flush()?;
+        self.written += incoming;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn compress_file(from: &Path, to: &Path) -> io::Result<()> {
+    let mut input = File::open(from)?;
+    let mut encoder = GzEncoder::new(File::create(to)?, Compression::default());
+    io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?;
+    fs::remove_file(from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logger::LogLevel;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    fn temp_log(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cli_core_file_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir.join("app.log")
+    }
+
+    fn record(message: &str) -> Record {
+        Record::new(LogLevel::Info, None, message, &[])
+    }
+
+    #[test]
+    fn size_rotation_keeps_max_files() {
+        let path = temp_log("size");
+        let line_len = record("message 0").to_plain_line().len() as u64 + 1;
+        let mut sink = FileSink::new(&path).unwrap().with_rotation(Rotation::Size(line_len * 2)).with_max_files(2);
+        for i in 0..7 {
+            sink.write(&record(&format!("message {}", i))).unwrap();
+        }
+
+        // Two lines per file, newest in the active one
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 1);
+        assert!(fs::read_to_string(&path).unwrap().contains("message 6"));
+        assert!(fs::read_to_string(sink.rotated_path(1, false)).unwrap().contains("message 5"));
+        assert!(fs::read_to_string(sink.rotated_path(2, false)).unwrap().contains("message 3"));
+        assert!(!sink.rotated_path(3, false).exists());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn rotation_only_touches_existing_files() {
+        let path = temp_log("listed");
+        let mut sink = FileSink::new(&path).unwrap().with_rotation(Rotation::Size(1)).with_max_files(usize::MAX);
+        fs::write(sink.rotated_path(7, false), "old").unwrap();
+        fs::write(path.with_file_name("app.log.bak"), "unrelated").unwrap();
+        sink.write(&record("first")).unwrap();
+        sink.write(&record("second")).unwrap();
+
+        assert_eq!(sink.rotated_files().unwrap(), [(8, false), (1, false)]);
+        assert!(path.with_file_name("app.log.bak").exists());
+
+        // Lowering the limit prunes everything past it on the next rotation
+        sink.max_files = 2;
+        sink.write(&record("third")).unwrap();
+        assert_eq!(sink.rotated_files().unwrap(), [(2, false), (1, false)]);
+        assert!(fs::read_to_string(sink.rotated_path(2, false)).unwrap().contains("first"));
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn rotated_files_can_be_compressed() {
+        let path = temp_log("gzip");
+        let mut sink = FileSink::new(&path).unwrap().with_rotation(Rotation::Size(1)).with_compression(true);
+        sink.write(&record("first")).unwrap();
+        sink.write(&record("second")).unwrap();
+
+        assert!(!sink.rotated_path(1, false).exists());
+        let mut decompressed = String::new();
+        GzDecoder::new(File::open(sink.rotated_path(1, true)).unwrap())
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert!(decompressed.contains("first"));
+        assert!(fs::read_to_string(&path).unwrap().contains("second"));
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn daily_rotation_starts_a_new_file_on_a_new_day() {
+        let path = temp_log("daily");
+        let mut sink = FileSink::new(&path).unwrap().with_rotation(Rotation::Daily);
+        sink.write(&record("today")).unwrap();
+        assert!(!sink.rotated_path(1, false).exists());
+
+        sink.opened_on = sink.opened_on.pred_opt().unwrap();
+        sink.write(&record("tomorrow")).unwrap();
+        assert!(fs::read_to_string(sink.rotated_path(1, false)).unwrap().contains("today"));
+        assert!(!fs::read_to_string(&path).unwrap().contains("today"));
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn reopening_continues_the_size_count() {
+        let path = temp_log("reopen");
+        FileSink::new(&path).unwrap().write(&record("before restart")).unwrap();
+        let sink = FileSink::new(&path).unwrap();
+        assert_eq!(sink.written, fs::metadata(&path).unwrap().len());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+}
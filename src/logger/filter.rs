@@ -0,0 +1,139 @@
+use std::str::FromStr;
+
+use super::LogLevel;
+use crate::errors::{CliError, config_error};
+
+pub const LOG_ENV_VAR: &str = "CLI_CORE_LOG";
+
+// Which levels are shown, optionally per target.
+//
+// Spec syntax: comma separated directives, either a bare level that applies to
+// everything (`info`) or `target=level` (`mytool::net=debug`). A directive for
+// `mytool::net` also covers `mytool::net::tcp`; the longest matching target wins.
+// `off` disables output entirely.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LevelFilter {
+    default: Option<LogLevel>,
+    directives: Vec<(String, Option<LogLevel>)>,
+}
+
+impl LevelFilter {
+    pub fn new(default: LogLevel) -> Self {
+        LevelFilter {
+            default: Some(default),
+            directives: Vec::new(),
+        }
+    }
+
+    pub fn parse(spec: &str) -> Result<Self, CliError> {
+        let mut filter = LevelFilter::new(LogLevel::Info);
+
+        for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            match directive.split_once('=') {
+                Some((target, level)) => {
+                    let target = target.trim();
+                    if target.is_empty() {
+                        return Err(config_error(format!("ログレベル指定のターゲットが空です: {}", directive)));
+                    }
+                    filter.directives.push((target.to_string(), parse_threshold(level)?));
+                }
+                None => filter.default = parse_threshold(directive)?,
+            }
+        }
+
+        // Longest targets first so the most specific directive matches
+        filter.directives.sort_by_key(|(target, _)| std::cmp::Reverse(target.len()));
+        Ok(filter)
+    }
+
+    pub fn enabled(&self, level: LogLevel, target: Option<&str>) -> bool {
+        let threshold = target
+            .and_then(|target| {
+                self.directives
+                    .iter()
+                    .find(|(prefix, _)| target_matches(target, prefix))
+                    .map(|(_, threshold)| *threshold)
+            })
+            .unwrap_or(self.default);
+
+        match threshold {
+            Some(min) => level >= min,
+            None => false,
+        }
+    }
+}
+
+impl Default for LevelFilter {
+    fn default() -> Self {
+        LevelFilter::new(LogLevel::Debug)
+    }
+}
+
+impl FromStr for LevelFilter {
+    type Err = CliError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LevelFilter::parse(s)
+    }
+}
+
+fn target_matches(target: &str, prefix: &str) -> bool {
+    target == prefix || target.strip_prefix(prefix).is_some_and(|rest| rest.starts_with("::"))
+}
+
+fn parse_threshold(name: &str) -> Result<Option<LogLevel>, CliError> {
+    match name.trim().to_lowercase().as_str() {
+        "off" | "none" => Ok(None),
+        other => other
+            .parse::<LogLevel>()
+            .map(Some)
+            .map_err(config_error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bare_level_sets_the_default() {
+        let filter = LevelFilter::parse("warn").unwrap();
+        assert!(filter.enabled(LogLevel::Error, None));
+        assert!(filter.enabled(LogLevel::Warning, Some("any::target")));
+        assert!(!filter.enabled(LogLevel::Info, None));
+    }
+
+    #[test]
+    fn most_specific_target_wins() {
+        let filter = LevelFilter::parse("info, mytool=warn, mytool::net=trace").unwrap();
+        assert!(filter.enabled(LogLevel::Trace, Some("mytool::net")));
+        assert!(filter.enabled(LogLevel::Trace, Some("mytool::net::tcp")));
+        assert!(!filter.enabled(LogLevel::Info, Some("mytool::db")));
+        assert!(filter.enabled(LogLevel::Info, Some("other")));
+        // A prefix only matches whole path segments
+        assert!(!filter.enabled(LogLevel::Trace, Some("mytool::network")));
+    }
+
+    #[test]
+    fn off_disables_output() {
+        let filter = LevelFilter::parse("off,audit=info").unwrap();
+        assert!(!filter.enabled(LogLevel::Fatal, None));
+        assert!(filter.enabled(LogLevel::Info, Some("audit")));
+
+        let filter: LevelFilter = "debug,noisy=OFF".parse().unwrap();
+        assert!(!filter.enabled(LogLevel::Error, Some("noisy")));
+        assert!(filter.enabled(LogLevel::Debug, None));
+    }
+
+    #[test]
+    fn empty_spec_is_info() {
+        assert_eq!(LevelFilter::parse(" , ").unwrap(), LevelFilter::new(LogLevel::Info));
+    }
+
+    #[test]
+    fn invalid_specs_are_errors() {
+        assert!(LevelFilter::parse("loud").is_err());
+        assert!(LevelFilter::parse("=debug").is_err());
+        assert!(LevelFilter::parse("net=loud").is_err());
+    }
+}
@@ -0,0 +1,175 @@
+use chrono::format::{Item, StrftimeItems};
+use colored::*;
+
+use super::{LogLevel, Record, ScopeStyle, scope};
+use crate::color::{Stream, paint};
+use crate::errors::{CliError, config_error};
+use crate::placeholder::{Token, tokenize};
+
+pub const DEFAULT_FORMAT: &str = "{timestamp} [{level}] {message} {fields}";
+const DEFAULT_TIMESTAMP: &str = "%Y-%m-%d %H:%M:%S";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Timestamp(String),
+    Level,
+    Program,
+    Target,
+    Message,
+    Fields,
+}
+
+// Parsed console line layout.
+//
+// Placeholders: `{timestamp}` or `{timestamp:<strftime>}`, `{level}`, `{program}`,
+// `{target}`, `{message}` and `{fields}`. Use `{{` and `}}` for literal braces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogFormat {
+    segments: Vec<Segment>,
+}
+
+impl LogFormat {
+    pub fn parse(template: &str) -> Result<Self, CliError> {
+        let segments = tokenize(template, "ログフォーマット")?
+            .into_iter()
+            .map(|token| match token {
+                Token::Literal(text) => Ok(Segment::Literal(text)),
+                Token::Placeholder(name) => parse_placeholder(&name),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(LogFormat { segments })
+    }
+
+    pub fn render(&self, record: &Record, program: &str, stream: Stream) -> String {
+        let mut line = String::new();
+
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => line.push_str(text),
+                Segment::Timestamp(format) => {
+                    let timestamp = record.timestamp.format(format).to_string();
+                    line.push_str(&paint(timestamp.bright_black(), stream));
+                }
+                Segment::Level => line.push_str(&paint(level_style(record.level), stream)),
+                Segment::Program => line.push_str(program),
+                Segment::Target => line.push_str(record.target.as_deref().unwrap_or("")),
+                Segment::Message => {
+                    line.push_str(&scope_prefix(record, stream));
+                    line.push_str(&record.message);
+                }
+                Segment::Fields => line.push_str(&record.fields_string()),
+            }
+        }
+
+        line.trim_end().to_string()
+    }
+}
+
+impl Default for LogFormat {
+    fn default() -> Self {
+        LogFormat::parse(DEFAULT_FORMAT).expect("default log format is valid")
+    }
+}
+
+fn parse_placeholder(name: &str) -> Result<Segment, CliError> {
+    let (key, arg) = match name.split_once(':') {
+        Some((key, arg)) => (key, Some(arg)),
+        None => (name, None),
+    };
+
+    match (key.trim(), arg) {
+        ("timestamp", format) => {
+            let format = format.unwrap_or(DEFAULT_TIMESTAMP);
+            if StrftimeItems::new(format).any(|item| item == Item::Error) {
+                return Err(config_error(format!("タイムスタンプの書式が不正です: {}", format)));
+            }
+            Ok(Segment::Timestamp(format.to_string()))
+        }
+        ("level", None) => Ok(Segment::Level),
+        ("program", None) => Ok(Segment::Program),
+        ("target", None) => Ok(Segment::Target),
+        ("message", None) => Ok(Segment::Message),
+        ("fields", None) => Ok(Segment::Fields),
+        _ => Err(config_error(format!("不明なログフォーマットのプレースホルダーです: {{{}}}", name))),
+    }
+}
+
+fn level_style(level: LogLevel) -> ColoredString {
+    let label = level.as_str();
+    match level {
+        LogLevel::Trace => label.dimmed(),
+        LogLevel::Debug => label.bright_black(),
+        LogLevel::Info => label.bright_blue(),
+        LogLevel::Success => label.bright_green(),
+        LogLevel::Warning => label.bright_yellow(),
+        LogLevel::Error => label.bright_red(),
+        LogLevel::Fatal => label.white().on_red().bold(),
+    }
+}
+
+fn scope_prefix(record: &Record, stream: Stream) -> String {
+    if record.scope.is_empty() {
+        return String::new();
+    }
+    match scope::scope_style() {
+        ScopeStyle::Indent => "  ".repeat(record.scope.len()),
+        ScopeStyle::Path => format!("{} ", paint(format!("[{}]", record.scope_path()).bright_black(), stream)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_placeholders_and_literals() {
+        let format = LogFormat::parse("{{{program}}} {target}: {message} {fields}").unwrap();
+        assert_eq!(
+            format.segments,
+            vec![
+                Segment::Literal("{".to_string()),
+                Segment::Program,
+                Segment::Literal("} ".to_string()),
+                Segment::Target,
+                Segment::Literal(": ".to_string()),
+                Segment::Message,
+                Segment::Literal(" ".to_string()),
+                Segment::Fields,
+            ]
+        );
+    }
+
+    #[test]
+    fn timestamp_takes_an_optional_strftime_format() {
+        let format = LogFormat::parse("{timestamp}|{timestamp:%H:%M}").unwrap();
+        assert_eq!(
+            format.segments,
+            vec![
+                Segment::Timestamp(DEFAULT_TIMESTAMP.to_string()),
+                Segment::Literal("|".to_string()),
+                Segment::Timestamp("%H:%M".to_string()),
+            ]
+        );
+        assert!(LogFormat::parse("{timestamp:%Q}").is_err());
+    }
+
+    #[test]
+    fn malformed_templates_are_errors() {
+        assert!(LogFormat::parse("{message").is_err());
+        assert!(LogFormat::parse("message}").is_err());
+        assert!(LogFormat::parse("{unknown}").is_err());
+        assert!(LogFormat::parse("{level:bold}").is_err());
+    }
+
+    #[test]
+    fn renders_uncolored_segments() {
+        let format = LogFormat::parse("{program}/{target} {message} {fields}").unwrap();
+        let record = Record::new(LogLevel::Info, Some("net"), "connected", &[("host", "a"), ("port", "80")]);
+        assert_eq!(format.render(&record, "tool", Stream::Stdout), "tool/net connected host=a port=80");
+
+        // Trailing space left by empty fields is trimmed
+        let record = Record::new(LogLevel::Info, None, "done", &[]);
+        assert_eq!(format.render(&record, "tool", Stream::Stdout), "tool/ done");
+    }
+}
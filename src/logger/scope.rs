@@ -0,0 +1,170 @@
+use std::cell::RefCell;
+use std::ffi::CStr;
+use std::marker::PhantomData;
+use std::os::raw::c_char;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::{Duration, Instant};
+
+use super::{LogLevel, log_with};
+
+// How the console shows the active scopes in front of a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeStyle {
+    // Two spaces per nesting level
+    Indent,
+    // `[deploy > build] message`
+    Path,
+}
+
+static SCOPE_STYLE: AtomicU8 = AtomicU8::new(0);
+
+thread_local! {
+    static SCOPES: RefCell<Vec<(String, Instant)>> = const { RefCell::new(Vec::new()) };
+}
+
+pub fn set_scope_style(style: ScopeStyle) {
+    let value = match style {
+        ScopeStyle::Indent => 0,
+        ScopeStyle::Path => 1,
+    };
+    SCOPE_STYLE.store(value, Ordering::Relaxed);
+}
+
+pub fn scope_style() -> ScopeStyle {
+    match SCOPE_STYLE.load(Ordering::Relaxed) {
+        1 => ScopeStyle::Path,
+        _ => ScopeStyle::Indent,
+    }
+}
+
+// Names of the scopes open on the current thread, outermost first
+pub fn current_scope() -> Vec<String> {
+    SCOPES.with(|scopes| scopes.borrow().iter().map(|(name, _)| name.clone()).collect())
+}
+
+// Closes its scope when dropped. Scopes are per thread, so the guard is not `Send`.
+pub struct ScopeGuard {
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        end_scope();
+    }
+}
+
+// Open a named scope; messages logged until the guard drops are nested under it
+pub fn scope(name: &str) -> ScopeGuard {
+    begin_scope(name);
+    ScopeGuard { _not_send: PhantomData }
+}
+
+pub fn begin_scope(name: &str) {
+    SCOPES.with(|scopes| scopes.borrow_mut().push((name.to_string(), Instant::now())));
+}
+
+// Close the innermost scope, logging how long it was open with `scope` and
+// `elapsed_ms` fields
+pub fn end_scope() -> Option<Duration> {
+    let (name, elapsed) =
+        SCOPES.with(|scopes| scopes.borrow().last().map(|(name, start)| (name.clone(), start.elapsed())))?;
+    let elapsed_ms = elapsed.as_millis().to_string();
+    log_with(
+        LogLevel::Info,
+        None,
+        &format!("{} 完了 ({})", name, format_duration(elapsed)),
+        &[("scope", &name), ("elapsed_ms", &elapsed_ms)],
+    );
+    SCOPES.with(|scopes| scopes.borrow_mut().pop());
+    Some(elapsed)
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs_f64();
+    if secs < 1.0 {
+        format!("{}ms", duration.as_millis())
+    } else {
+        format!("{:.2}s", secs)
+    }
+}
+
+// C interface
+
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_scope_begin(name: *const c_char) {
+    let c_name = unsafe {
+        if name.is_null() {
+            return;
+        }
+        CStr::from_ptr(name)
+    };
+
+    if let Ok(name_str) = c_name.to_str() {
+        begin_scope(name_str);
+    }
+}
+
+// Returns the elapsed time of the closed scope in seconds, or a negative value if none was open
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_scope_end() -> f64 {
+    match end_scope() {
+        Some(elapsed) => elapsed.as_secs_f64(),
+        None => -1.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logger::{capture_logs, log_info};
+
+    #[test]
+    fn guards_nest_and_unwind() {
+        let capture = capture_logs();
+        {
+            let _outer = scope("outer");
+            {
+                let _inner = scope("inner");
+                assert_eq!(current_scope(), ["outer", "inner"]);
+                log_info("working");
+            }
+            assert_eq!(current_scope(), ["outer"]);
+        }
+        assert!(current_scope().is_empty());
+
+        let records = capture.records();
+        assert_eq!(records[0].scope, ["outer", "inner"]);
+        assert_eq!(records[0].scope_path(), "outer > inner");
+        // Each closing line is logged from inside the scope it closes
+        assert_eq!(records[1].scope, ["outer", "inner"]);
+        assert!(records[1].message.starts_with("inner 完了"));
+        assert_eq!(records[2].scope, ["outer"]);
+        assert!(records[2].message.starts_with("outer 完了"));
+    }
+
+    #[test]
+    fn closing_line_carries_the_scope_and_elapsed_time() {
+        let capture = capture_logs();
+        begin_scope("build");
+        let elapsed = end_scope().unwrap();
+
+        let record = &capture.records()[0];
+        assert_eq!(record.level, LogLevel::Info);
+        assert!(capture.contains_field("scope", "build"));
+        assert!(capture.contains_field("elapsed_ms", &elapsed.as_millis().to_string()));
+    }
+
+    #[test]
+    fn ending_without_an_open_scope_does_nothing() {
+        let capture = capture_logs();
+        assert!(end_scope().is_none());
+        assert!(capture.is_empty());
+        assert!(cli_scope_end() < 0.0);
+    }
+
+    #[test]
+    fn short_durations_are_in_milliseconds() {
+        assert_eq!(format_duration(Duration::from_millis(250)), "250ms");
+        assert_eq!(format_duration(Duration::from_millis(1500)), "1.50s");
+    }
+}
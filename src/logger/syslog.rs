@@ -0,0 +1,343 @@
+use chrono::SecondsFormat;
+use std::ffi::CStr;
+use std::io;
+use std::os::raw::c_char;
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+
+use super::{LogLevel, LogSink, Record, program_name};
+
+pub const SYSLOG_SOCKET: &str = "/dev/log";
+pub const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+
+// SD-ID used for structured fields (32473 is the example enterprise number from RFC 5424)
+const SD_ID: &str = "fields@32473";
+
+// Syslog/journald severity for a level
+pub fn syslog_severity(level: LogLevel) -> u8 {
+    match level {
+        LogLevel::Trace | LogLevel::Debug => 7,
+        LogLevel::Info => 6,
+        LogLevel::Success => 5,
+        LogLevel::Warning => 4,
+        LogLevel::Error => 3,
+        LogLevel::Fatal => 2,
+    }
+}
+
+// Sends RFC 5424 formatted records to the local syslog datagram socket
+pub struct SyslogSink {
+    socket: UnixDatagram,
+    path: PathBuf,
+    facility: u8,
+    app_name: Option<String>,
+    hostname: String,
+}
+
+impl SyslogSink {
+    pub fn new() -> io::Result<Self> {
+        Self::with_socket_path(SYSLOG_SOCKET)
+    }
+
+    // Use another socket, e.g. a stand-in bound by a test
+    pub fn with_socket_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(SyslogSink {
+            socket: UnixDatagram::unbound()?,
+            path: path.as_ref().to_path_buf(),
+            facility: 1,
+            app_name: None,
+            hostname: local_hostname(),
+        })
+    }
+
+    // Syslog facility code, 1 (user) by default; 16-23 are local0-local7
+    pub fn with_facility(mut self, facility: u8) -> Self {
+        self.facility = facility.min(23);
+        self
+    }
+
+    // APP-NAME field, defaults to the logger's program name
+    pub fn with_app_name(mut self, app_name: &str) -> Self {
+        self.app_name = Some(app_name.to_string());
+        self
+    }
+
+    pub fn format(&self, record: &Record) -> String {
+        let priority = self.facility as u32 * 8 + syslog_severity(record.level) as u32;
+        let app_name = self.app_name.clone().unwrap_or_else(program_name);
+
+        let mut params: Vec<(String, String)> = record.fields.clone();
+        if !record.scope.is_empty() {
+            params.push(("scope".to_string(), record.scope_path()));
+        }
+        let structured_data = if params.is_empty() {
+            "-".to_string()
+        } else {
+            let params = params
+                .iter()
+                .map(|(key, value)| format!(" {}=\"{}\"", sd_name(key), escape_sd_value(value)))
+                .collect::<String>();
+            format!("[{}{}]", SD_ID, params)
+        };
+
+        format!(
+            "<{}>1 {} {} {} {} {} {} {}",
+            priority,
+            record.timestamp.to_rfc3339_opts(SecondsFormat::Micros, false),
+            header_field(&self.hostname, 255),
+            header_field(&app_name, 48),
+            std::process::id(),
+            header_field(record.target.as_deref().unwrap_or(""), 32),
+            structured_data,
+            record.message
+        )
+    }
+}
+
+impl LogSink for SyslogSink {
+    fn write(&mut self, record: &Record) -> io::Result<()> {
+        self.socket.send_to(self.format(record).as_bytes(), &self.path)?;
+        Ok(())
+    }
+}
+
+// Sends records to journald using its native protocol, keeping fields structured
+pub struct JournaldSink {
+    socket: UnixDatagram,
+    path: PathBuf,
+    identifier: Option<String>,
+}
+
+impl JournaldSink {
+    pub fn new() -> io::Result<Self> {
+        Self::with_socket_path(JOURNALD_SOCKET)
+    }
+
+    pub fn with_socket_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(JournaldSink {
+            socket: UnixDatagram::unbound()?,
+            path: path.as_ref().to_path_buf(),
+            identifier: None,
+        })
+    }
+
+    // SYSLOG_IDENTIFIER, defaults to the logger's program name
+    pub fn with_identifier(mut self, identifier: &str) -> Self {
+        self.identifier = Some(identifier.to_string());
+        self
+    }
+
+    pub fn encode(&self, record: &Record) -> Vec<u8> {
+        let mut payload = Vec::new();
+        let identifier = self.identifier.clone().unwrap_or_else(program_name);
+
+        push_journal_field(&mut payload, "MESSAGE", &record.message);
+        push_journal_field(&mut payload, "PRIORITY", &syslog_severity(record.level).to_string());
+        push_journal_field(&mut payload, "SYSLOG_IDENTIFIER", &identifier);
+        push_journal_field(&mut payload, "SYSLOG_PID", &std::process::id().to_string());
+        push_journal_field(&mut payload, "CLI_CORE_LEVEL", record.level.as_str());
+        if let Some(target) = &record.target {
+            push_journal_field(&mut payload, "CLI_CORE_TARGET", target);
+        }
+        if !record.scope.is_empty() {
+            push_journal_field(&mut payload, "CLI_CORE_SCOPE", &record.scope_path());
+        }
+        for (key, value) in &record.fields {
+            if let Some(name) = journal_field_name(key) {
+                push_journal_field(&mut payload, &name, value);
+            }
+        }
+        payload
+    }
+}
+
+impl LogSink for JournaldSink {
+    fn write(&mut self, record: &Record) -> io::Result<()> {
+        self.socket.send_to(&self.encode(record), &self.path)?;
+        Ok(())
+    }
+}
+
+// RFC 5424 header fields are printable ASCII without spaces, "-" when empty
+fn header_field(value: &str, max_len: usize) -> String {
+    let cleaned: String = value.chars().filter(|c| c.is_ascii_graphic()).take(max_len).collect();
+    if cleaned.is_empty() { "-".to_string() } else { cleaned }
+}
+
+fn sd_name(key: &str) -> String {
+    let cleaned: String = key
+        .chars()
+        .filter(|c| c.is_ascii_graphic() && !matches!(c, '=' | ']' | '"'))
+        .take(32)
+        .collect();
+    if cleaned.is_empty() { "_".to_string() } else { cleaned }
+}
+
+fn escape_sd_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// Journal field names are upper case letters, digits and '_', and must not start with '_' or a digit
+fn journal_field_name(key: &str) -> Option<String> {
+    let name: String = key
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect();
+    let name = name.trim_start_matches(|c: char| c == '_' || c.is_ascii_digit()).to_string();
+    if name.is_empty() { None } else { Some(name) }
+}
+
+fn push_journal_field(payload: &mut Vec<u8>, name: &str, value: &str) {
+    payload.extend_from_slice(name.as_bytes());
+    if value.contains('\n') {
+        // Binary-safe form: NAME\n<little endian u64 length><value>\n
+        payload.push(b'\n');
+        payload.extend_from_slice(&(value.len() as u64).to_le_bytes());
+        payload.extend_from_slice(value.as_bytes());
+    } else {
+        payload.push(b'=');
+        payload.extend_from_slice(value.as_bytes());
+    }
+    payload.push(b'\n');
+}
+
+fn local_hostname() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|name| name.trim().to_string())
+        .ok()
+        .filter(|name| !name.is_empty())
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .unwrap_or_default()
+}
+
+// C interface
+
+fn socket_path(path: *const c_char, default: &str) -> Option<String> {
+    if path.is_null() {
+        return Some(default.to_string());
+    }
+    unsafe { CStr::from_ptr(path) }.to_str().ok().map(|p| p.to_string())
+}
+
+// Pass NULL to use /dev/log
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_add_syslog_sink(path: *const c_char) -> bool {
+    match socket_path(path, SYSLOG_SOCKET).map(SyslogSink::with_socket_path) {
+        Some(Ok(sink)) => {
+            super::add_sink(sink);
+            true
+        }
+        _ => false,
+    }
+}
+
+// Pass NULL to use /run/systemd/journal/socket
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_add_journald_sink(path: *const c_char) -> bool {
+    match socket_path(path, JOURNALD_SOCKET).map(JournaldSink::with_socket_path) {
+        Some(Ok(sink)) => {
+            super::add_sink(sink);
+            true
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A datagram socket standing in for /dev/log or the journal socket
+    fn stand_in(name: &str) -> (UnixDatagram, PathBuf) {
+        let path = std::env::temp_dir().join(format!("cli_core_{}_{}.sock", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let socket = UnixDatagram::bind(&path).unwrap();
+        (socket, path)
+    }
+
+    fn receive(socket: &UnixDatagram) -> Vec<u8> {
+        let mut buf = vec![0; 4096];
+        let len = socket.recv(&mut buf).unwrap();
+        buf.truncate(len);
+        buf
+    }
+
+    #[test]
+    fn syslog_sends_rfc5424_lines() {
+        let (socket, path) = stand_in("syslog");
+        let mut sink = SyslogSink::with_socket_path(&path).unwrap().with_facility(16).with_app_name("my app");
+        let record = Record::new(LogLevel::Warning, Some("net"), "disk almost full", &[("free", "3%")]);
+        sink.write(&record).unwrap();
+
+        let line = String::from_utf8(receive(&socket)).unwrap();
+        let fields: Vec<&str> = line.splitn(8, ' ').collect();
+        // local0 (16) * 8 + warning (4)
+        assert_eq!(fields[0], "<132>1");
+        assert_eq!(fields[3], "myapp");
+        assert_eq!(fields[4], std::process::id().to_string());
+        assert_eq!(fields[5], "net");
+        assert_eq!(fields[6], "[fields@32473");
+        assert!(line.ends_with(" free=\"3%\"] disk almost full"));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn syslog_without_fields_uses_nil_values() {
+        let sink = SyslogSink::with_socket_path("/nonexistent").unwrap().with_app_name("app");
+        let record = Record::new(LogLevel::Info, None, "hello", &[]);
+        let line = sink.format(&record);
+        assert!(line.starts_with("<14>1 "));
+        assert!(line.ends_with(" - - hello"));
+    }
+
+    #[test]
+    fn structured_data_is_escaped() {
+        assert_eq!(escape_sd_value(r#"a "b" \c] d"#), r#"a \"b\" \\c\] d"#);
+        assert_eq!(sd_name("user id=\"x\"]"), "useridx");
+        assert_eq!(sd_name(" "), "_");
+        assert_eq!(header_field("", 10), "-");
+        assert_eq!(header_field("a b\tc", 2), "ab");
+    }
+
+    #[test]
+    fn journald_sends_native_fields() {
+        let (socket, path) = stand_in("journald");
+        let mut sink = JournaldSink::with_socket_path(&path).unwrap().with_identifier("tool");
+        let record = Record::new(LogLevel::Error, Some("db"), "query failed", &[("request-id", "42")]);
+        sink.write(&record).unwrap();
+
+        let payload = String::from_utf8(receive(&socket)).unwrap();
+        let lines: Vec<&str> = payload.lines().collect();
+        assert!(lines.contains(&"MESSAGE=query failed"));
+        assert!(lines.contains(&"PRIORITY=3"));
+        assert!(lines.contains(&"SYSLOG_IDENTIFIER=tool"));
+        assert!(lines.contains(&"CLI_CORE_TARGET=db"));
+        assert!(lines.contains(&"REQUEST_ID=42"));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn journald_multiline_values_are_length_prefixed() {
+        let mut payload = Vec::new();
+        push_journal_field(&mut payload, "MESSAGE", "line one\nline two");
+
+        let mut expected = b"MESSAGE\n".to_vec();
+        expected.extend_from_slice(&17u64.to_le_bytes());
+        expected.extend_from_slice(b"line one\nline two\n");
+        assert_eq!(payload, expected);
+    }
+
+    #[test]
+    fn journal_field_names_are_sanitised() {
+        assert_eq!(journal_field_name("user.name").as_deref(), Some("USER_NAME"));
+        assert_eq!(journal_field_name("_9lives").as_deref(), Some("LIVES"));
+        assert_eq!(journal_field_name("__"), None);
+    }
+}
@@ -0,0 +1,347 @@
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::{LogLevel, Record};
+
+type MessageKey = (LogLevel, Option<String>, String);
+
+// How often the timer looks for windows that have closed
+const TIMER_TICK: Duration = Duration::from_millis(200);
+// A run of repeats is summarised once the message has been quiet this long
+const REPEAT_QUIET: Duration = Duration::from_secs(1);
+
+struct Window {
+    start: Instant,
+    count: u32,
+    suppressed: u64,
+}
+
+struct RateLimit {
+    max_per_window: u32,
+    window: Duration,
+}
+
+struct ThrottleState {
+    dedup: bool,
+    last: Option<MessageKey>,
+    last_seen: Option<Instant>,
+    repeats: u64,
+    rate_limit: Option<RateLimit>,
+    windows: HashMap<MessageKey, Window>,
+    timer_running: bool,
+}
+
+impl ThrottleState {
+    fn enabled(&self) -> bool {
+        self.dedup || self.rate_limit.is_some()
+    }
+}
+
+lazy_static! {
+    static ref STATE: Mutex<ThrottleState> = Mutex::new(ThrottleState {
+        dedup: false,
+        last: None,
+        last_seen: None,
+        repeats: 0,
+        rate_limit: None,
+        windows: HashMap::new(),
+        timer_running: false,
+    });
+}
+
+// Collapse identical consecutive messages into a "repeated N times" line,
+// logged when a different message arrives or the message has been quiet for
+// a second
+pub fn set_dedup(enabled: bool) {
+    if let Ok(mut state) = STATE.lock() {
+        state.dedup = enabled;
+        if !enabled {
+            state.last = None;
+            state.repeats = 0;
+        }
+        start_timer(&mut state);
+    }
+}
+
+// Allow at most `max_per_window` identical messages per `window`; the number
+// suppressed is logged once the window closes. `None` turns the limit off.
+//
+// Summaries are logged from a timer thread, so they don't wait for the next
+// message. That also means they bypass `capture_logs`, which only sees its own
+// thread. Counts still pending when the program ends are only logged by
+// `flush_logs` or `shutdown_logging`.
+pub fn set_rate_limit(limit: Option<(u32, Duration)>) {
+    if let Ok(mut state) = STATE.lock() {
+        state.rate_limit = limit.map(|(max_per_window, window)| RateLimit { max_per_window, window });
+        state.windows.clear();
+        start_timer(&mut state);
+    }
+}
+
+// The timer stops by itself once both dedup and rate limiting are off
+fn start_timer(state: &mut ThrottleState) {
+    if !state.enabled() || state.timer_running {
+        return;
+    }
+    let spawned = thread::Builder::new()
+        .name("cli_core-log-throttle".to_string())
+        .spawn(|| {
+            while let Some(records) = expire() {
+                for record in records {
+                    super::emit(record);
+                }
+                thread::sleep(TIMER_TICK);
+            }
+        });
+    state.timer_running = spawned.is_ok();
+}
+
+// Summaries for windows and runs of repeats that have ended by now, or None
+// once there is nothing left to throttle
+fn expire() -> Option<Vec<Record>> {
+    let Ok(mut state) = STATE.lock() else {
+        return None;
+    };
+    if !state.enabled() {
+        state.timer_running = false;
+        return None;
+    }
+    let now = Instant::now();
+    let mut out = close_windows(&mut state, now);
+    let quiet = state.last_seen.is_some_and(|seen| now.duration_since(seen) >= REPEAT_QUIET);
+    if state.repeats > 0 && quiet {
+        out.extend(repeat_summary(&mut state));
+        // The next occurrence is printed again rather than counted
+        state.last = None;
+    }
+    Some(out)
+}
+
+// Decide what to emit for `record`: any pending summaries, followed by the
+// record itself unless it is a repeat or over the limit
+pub(super) fn admit(record: Record) -> Vec<Record> {
+    match STATE.lock() {
+        Ok(mut state) => admit_at(&mut state, record, Instant::now()),
+        Err(_) => vec![record],
+    }
+}
+
+fn admit_at(state: &mut ThrottleState, record: Record, now: Instant) -> Vec<Record> {
+    if !state.enabled() {
+        return vec![record];
+    }
+
+    let mut out = close_windows(state, now);
+    let key: MessageKey = (record.level, record.target.clone(), record.message.clone());
+
+    if state.dedup {
+        state.last_seen = Some(now);
+        if state.last.as_ref() == Some(&key) {
+            state.repeats += 1;
+            return out;
+        }
+        out.extend(repeat_summary(state));
+        state.last = Some(key.clone());
+    }
+
+    if let Some(limit) = &state.rate_limit {
+        let max_per_window = limit.max_per_window;
+        let window = state.windows.entry(key).or_insert(Window {
+            start: now,
+            count: 0,
+            suppressed: 0,
+        });
+        window.count += 1;
+        if window.count > max_per_window {
+            window.suppressed += 1;
+            return out;
+        }
+    }
+
+    out.push(record);
+    out
+}
+
+// Summaries still owed, e.g. before flushing or shutting down
+pub(super) fn drain() -> Vec<Record> {
+    let Ok(mut state) = STATE.lock() else {
+        return Vec::new();
+    };
+
+    let mut out: Vec<Record> = repeat_summary(&mut state).into_iter().collect();
+    state.last = None;
+    for (key, window) in state.windows.drain() {
+        if window.suppressed > 0 {
+            out.push(suppressed_summary(&key, window.suppressed));
+        }
+    }
+    out
+}
+
+fn close_windows(state: &mut ThrottleState, now: Instant) -> Vec<Record> {
+    let Some(limit) = &state.rate_limit else {
+        return Vec::new();
+    };
+    let window_length = limit.window;
+
+    let mut out = Vec::new();
+    state.windows.retain(|key, window| {
+        if now.duration_since(window.start) < window_length {
+            return true;
+        }
+        if window.suppressed > 0 {
+            out.push(suppressed_summary(key, window.suppressed));
+        }
+        false
+    });
+    out
+}
+
+fn repeat_summary(state: &mut ThrottleState) -> Option<Record> {
+    let repeats = std::mem::take(&mut state.repeats);
+    let (level, target, _) = state.last.as_ref()?;
+    if repeats == 0 {
+        return None;
+    }
+    Some(Record::new(
+        *level,
+        target.as_deref(),
+        &format!("直前のメッセージが {} 回繰り返されました", repeats),
+        &[],
+    ))
+}
+
+fn suppressed_summary(key: &MessageKey, suppressed: u64) -> Record {
+    let (level, target, message) = key;
+    Record::new(
+        *level,
+        target.as_deref(),
+        &format!("同じメッセージを {} 件抑制しました: {}", suppressed, message),
+        &[],
+    )
+}
+
+// C interface
+
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_set_log_dedup(enabled: bool) {
+    set_dedup(enabled);
+}
+
+// A `max_per_window` of 0 turns rate limiting off
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_set_log_rate_limit(max_per_window: u32, window_ms: u64) {
+    if max_per_window == 0 {
+        set_rate_limit(None);
+    } else {
+        set_rate_limit(Some((max_per_window, Duration::from_millis(window_ms))));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(dedup: bool, rate_limit: Option<(u32, Duration)>) -> ThrottleState {
+        ThrottleState {
+            dedup,
+            last: None,
+            last_seen: None,
+            repeats: 0,
+            rate_limit: rate_limit.map(|(max_per_window, window)| RateLimit { max_per_window, window }),
+            windows: HashMap::new(),
+            timer_running: false,
+        }
+    }
+
+    fn key(message: &str) -> MessageKey {
+        (LogLevel::Warning, None, message.to_string())
+    }
+
+    #[test]
+    fn closed_windows_report_suppressed_counts() {
+        let mut state = state(false, Some((2, Duration::from_secs(1))));
+        let start = Instant::now();
+        state.windows.insert(key("busy"), Window { start, count: 5, suppressed: 3 });
+        state.windows.insert(key("quiet"), Window { start, count: 1, suppressed: 0 });
+
+        assert!(close_windows(&mut state, start + Duration::from_millis(500)).is_empty());
+        assert_eq!(state.windows.len(), 2);
+
+        let summaries = close_windows(&mut state, start + Duration::from_secs(1));
+        assert_eq!(summaries.len(), 1);
+        assert!(summaries[0].message.contains("3"));
+        assert!(summaries[0].message.contains("busy"));
+        assert!(state.windows.is_empty());
+    }
+
+    #[test]
+    fn repeat_summary_resets_the_count() {
+        let mut state = state(true, None);
+        assert!(repeat_summary(&mut state).is_none());
+
+        state.last = Some(key("again"));
+        state.repeats = 4;
+        let summary = repeat_summary(&mut state).unwrap();
+        assert_eq!(summary.level, LogLevel::Warning);
+        assert!(summary.message.contains("4"));
+        assert!(repeat_summary(&mut state).is_none());
+    }
+
+    fn record(message: &str) -> Record {
+        Record::new(LogLevel::Warning, None, message, &[])
+    }
+
+    fn messages(records: &[Record]) -> Vec<&str> {
+        records.iter().map(|record| record.message.as_str()).collect()
+    }
+
+    #[test]
+    fn repeats_are_counted_and_summarised_on_change() {
+        let mut state = state(true, None);
+        let now = Instant::now();
+
+        assert_eq!(messages(&admit_at(&mut state, record("again"), now)), ["again"]);
+        for _ in 0..3 {
+            assert!(admit_at(&mut state, record("again"), now).is_empty());
+        }
+        assert_eq!(state.repeats, 3);
+
+        let out = admit_at(&mut state, record("other"), now);
+        assert_eq!(out.len(), 2);
+        assert!(out[0].message.contains("3 回"));
+        assert_eq!(out[1].message, "other");
+        assert_eq!(state.repeats, 0);
+    }
+
+    #[test]
+    fn messages_over_the_limit_are_suppressed() {
+        let mut state = state(false, Some((2, Duration::from_secs(1))));
+        let start = Instant::now();
+
+        assert_eq!(admit_at(&mut state, record("busy"), start).len(), 1);
+        assert_eq!(admit_at(&mut state, record("busy"), start).len(), 1);
+        // The third in the window is held back, other messages still pass
+        assert!(admit_at(&mut state, record("busy"), start).is_empty());
+        assert!(admit_at(&mut state, record("busy"), start).is_empty());
+        assert_eq!(messages(&admit_at(&mut state, record("calm"), start)), ["calm"]);
+
+        // Once the window closes the count is reported ahead of the next message
+        let out = admit_at(&mut state, record("busy"), start + Duration::from_secs(1));
+        assert_eq!(out.len(), 2);
+        assert!(out[0].message.contains("2 件"));
+        assert_eq!(out[1].message, "busy");
+    }
+
+    #[test]
+    fn disabled_state_passes_everything() {
+        let mut state = state(false, None);
+        let now = Instant::now();
+        for _ in 0..3 {
+            assert_eq!(admit_at(&mut state, record("same"), now).len(), 1);
+        }
+    }
+}
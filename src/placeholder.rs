@@ -0,0 +1,89 @@
+use crate::errors::{CliError, config_error};
+
+// Piece of a `{placeholder}` template, shared by the log format and progress styles
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Token {
+    Literal(String),
+    // Text between the braces, left for the caller to interpret
+    Placeholder(String),
+}
+
+// Split `template` into literals and placeholders, with `{{` and `}}` as
+// literal braces. `kind` names the template in error messages.
+pub(crate) fn tokenize(template: &str, kind: &str) -> Result<Vec<Token>, CliError> {
+    let mut tokens = Vec::new();
+    let mut literal = String::new();
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                literal.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                literal.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(config_error(format!("{}の '{{' が閉じられていません: {}", kind, template))),
+                    }
+                }
+                if !literal.is_empty() {
+                    tokens.push(Token::Literal(std::mem::take(&mut literal)));
+                }
+                tokens.push(Token::Placeholder(name));
+            }
+            '}' => return Err(config_error(format!("{}に対応しない '}}' があります: {}", kind, template))),
+            c => literal.push(c),
+        }
+    }
+
+    if !literal.is_empty() {
+        tokens.push(Token::Literal(literal));
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn literal(text: &str) -> Token {
+        Token::Literal(text.to_string())
+    }
+
+    fn placeholder(name: &str) -> Token {
+        Token::Placeholder(name.to_string())
+    }
+
+    #[test]
+    fn splits_literals_and_placeholders() {
+        assert_eq!(tokenize("", "テンプレート").unwrap(), vec![]);
+        assert_eq!(
+            tokenize("a {x} b{y:10}", "テンプレート").unwrap(),
+            vec![literal("a "), placeholder("x"), literal(" b"), placeholder("y:10")]
+        );
+    }
+
+    #[test]
+    fn doubled_braces_are_literal() {
+        assert_eq!(
+            tokenize("{{{x}}}", "テンプレート").unwrap(),
+            vec![literal("{"), placeholder("x"), literal("}")]
+        );
+        assert_eq!(tokenize("{{}}", "テンプレート").unwrap(), vec![literal("{}")]);
+    }
+
+    #[test]
+    fn unbalanced_braces_name_the_template() {
+        let error = tokenize("{x", "ログフォーマット").unwrap_err().to_string();
+        assert!(error.contains("ログフォーマット"), "{}", error);
+        assert!(tokenize("x}", "テンプレート").is_err());
+    }
+}
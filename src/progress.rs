@@ -1,114 +1,634 @@
-use std::io::{self, Write, Stdout};
-use std::sync::{Arc, Mutex};
-use colored::*;
-use std::time::Instant;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, Weak};
+use std::time::{Duration, Instant};
+
+use crate::color::should_colorize;
+use crate::term;
+
+mod callback;
+mod estimator;
+mod events;
+mod io;
+mod iter;
+mod mode;
+mod multi;
+mod nested;
+mod style;
+mod suspend;
+mod target;
+
+pub use events::{ProgressEvent, ProgressEventKind, add_progress_event_writer, add_progress_event_callback, clear_progress_event_sinks};
+pub use io::{ProgressReader, ProgressWriter};
+pub use iter::{ProgressIter, ProgressIterator, parallel_map, parallel_map_with};
+pub use mode::{ProgressMode, set_progress_mode, progress_mode, set_plain_interval, plain_interval, DEFAULT_PLAIN_INTERVAL};
+pub use nested::{PARENT_SCALE, PARENT_TEMPLATE, PARENT_PLAIN_TEMPLATE};
+pub use suspend::{SuspendGuard, suspend, suspend_all};
+pub use target::ProgressTarget;
+pub use multi::{MultiProgress, create_multi_progress, add_to_multi_progress, finish_multi_progress};
+pub use style::{ProgressStyle, SpinnerFrames, format_bytes, format_duration, parse_color, DEFAULT_TEMPLATE, DEFAULT_SPINNER_TEMPLATE, BYTES_TEMPLATE, PLAIN_TEMPLATE, PLAIN_SPINNER_TEMPLATE, DEFAULT_CHARS, BLOCK_CHARS};
+use estimator::RateEstimator;
+use multi::MultiState;
+use nested::{CHILD_INDENT, Children};
+use style::RenderState;
+
+const DEFAULT_DRAW_RATE: u32 = 15;
+
+type DrawCallback = Box<dyn FnMut(&ProgressEvent) + Send>;
+
+// Where a bar is in its lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressState {
+    Running,
+    // Completed successfully
+    Finished,
+    // Given up on before completion; also what happens to a bar dropped while running
+    Abandoned,
+    // Stopped at the user's request
+    Cancelled,
+}
+
+impl ProgressState {
+    pub fn is_done(&self) -> bool {
+        *self != ProgressState::Running
+    }
+
+    fn event_kind(&self) -> ProgressEventKind {
+        match self {
+            ProgressState::Running => ProgressEventKind::Updated,
+            ProgressState::Finished => ProgressEventKind::Finished,
+            ProgressState::Abandoned => ProgressEventKind::Abandoned,
+            ProgressState::Cancelled => ProgressEventKind::Cancelled,
+        }
+    }
+}
 
 pub struct ProgressBar {
-    total: u64,
+    // None for a spinner whose total is not known yet
+    total: Option<u64>,
     current: u64,
-    width: usize,
     message: String,
+    prefix: String,
+    style: ProgressStyle,
     start_time: Instant,
-    stdout: Mutex<Stdout>,
-    finished: bool,
+    // Paused time to leave out of elapsed time: the bar's own pauses, and
+    // global suspensions since `suspend_offset`
+    paused_at: Option<Instant>,
+    paused_total: Duration,
+    suspend_offset: Duration,
+    estimator: RateEstimator,
+    state: ProgressState,
+    // Key of this bar's live line on the terminal
+    owner: u64,
+    target: ProgressTarget,
+    // Set when the bar is drawn as part of a MultiProgress
+    group: Option<Arc<MultiState>>,
+    // Set on a parent bar made of weighted sub-tasks
+    children: Option<Children>,
+    // Set on a sub-task; it is then drawn by the parent instead of on its own
+    parent: Option<Arc<Mutex<ProgressBar>>>,
+    // Replaces terminal drawing when set
+    callback: Option<DrawCallback>,
+    // Stop flag of the background ticker, if one is running
+    ticker: Option<Arc<AtomicBool>>,
+    // Minimum time between draws; zero draws on every change
+    draw_interval: Duration,
+    last_draw: Option<Instant>,
+    // When the last status line was printed in plain mode
+    last_plain: Option<Instant>,
 }
 
 impl ProgressBar {
     pub fn new(total: u64) -> Arc<Mutex<Self>> {
+        Self::with_length(Some(total))
+    }
+
+    // A bar for work of unknown size, drawn as a spinner until `set_length` is called
+    pub fn new_spinner() -> Arc<Mutex<Self>> {
+        Self::with_length(None)
+    }
+
+    // A bar counting bytes, drawn with `ProgressStyle::bytes()`
+    pub fn new_bytes(total: u64) -> Arc<Mutex<Self>> {
+        let bar = Self::new(total);
+        if let Ok(mut progress) = bar.lock() {
+            progress.style = ProgressStyle::bytes();
+        }
+        bar
+    }
+
+    // Wrap `reader` so reading from it advances this bar
+    pub fn wrap_read<R: std::io::Read>(this: &Arc<Mutex<Self>>, reader: R) -> ProgressReader<R> {
+        ProgressReader::new(reader, this.clone())
+    }
+
+    // Wrap `writer` so writing to it advances this bar
+    pub fn wrap_write<W: std::io::Write>(this: &Arc<Mutex<Self>>, writer: W) -> ProgressWriter<W> {
+        ProgressWriter::new(writer, this.clone())
+    }
+
+    fn with_length(total: Option<u64>) -> Arc<Mutex<Self>> {
+        let now = Instant::now();
         let progress = ProgressBar {
             total,
             current: 0,
-            width: 30,
             message: String::new(),
-            start_time: Instant::now(),
-            stdout: Mutex::new(io::stdout()),
-            finished: false,
+            prefix: String::new(),
+            style: ProgressStyle::default(),
+            start_time: now,
+            paused_at: None,
+            paused_total: Duration::ZERO,
+            suspend_offset: suspend::suspended_total(now),
+            estimator: RateEstimator::new(Duration::ZERO),
+            state: ProgressState::Running,
+            owner: term::next_owner_id(),
+            target: ProgressTarget::default(),
+            group: None,
+            children: None,
+            parent: None,
+            callback: None,
+            ticker: None,
+            draw_interval: Duration::from_secs(1) / DEFAULT_DRAW_RATE,
+            last_draw: None,
+            last_plain: None,
         };
 
+        progress.emit_event(ProgressEventKind::Created);
         Arc::new(Mutex::new(progress))
     }
 
+    // Unique id of this bar; also its id in the C API and in progress events
+    pub fn id(&self) -> u64 {
+        self.owner
+    }
+
     pub fn set_message(&mut self, message: &str) {
-        self.message = message.to_string();
+        self.replace_message(message);
+        self.redraw();
+    }
+
+    // Change the message without drawing
+    fn replace_message(&mut self, message: &str) {
+        if self.message != message {
+            self.message = message.to_string();
+            self.emit_event(ProgressEventKind::Message);
+        }
+    }
+
+    // Text for the `{prefix}` placeholder
+    pub fn set_prefix(&mut self, prefix: &str) {
+        self.prefix = prefix.to_string();
+        self.redraw();
+    }
+
+    pub fn set_style(&mut self, style: ProgressStyle) {
+        self.style = style;
+    }
+
+    pub fn style(&self) -> &ProgressStyle {
+        &self.style
+    }
+
+    // Where to draw the bar; stderr by default. A bar in a MultiProgress or
+    // under a parent is drawn wherever its group or parent is.
+    pub fn set_draw_target(&mut self, target: ProgressTarget) {
+        self.clear_live();
+        self.target = target;
+        self.last_draw = None;
+        self.last_plain = None;
+        if !self.state.is_done() {
+            self.redraw();
+        }
+    }
+
+    pub fn draw_target(&self) -> &ProgressTarget {
+        &self.target
+    }
+
+    pub fn length(&self) -> Option<u64> {
+        self.total
+    }
+
+    // Set the total, turning a spinner into a regular bar
+    pub fn set_length(&mut self, total: u64) {
+        self.total = Some(total);
+        self.current = self.current.min(total);
         self.redraw();
     }
 
     pub fn update(&mut self, current: u64) {
-        self.current = current;
-        if current > self.total {
-            self.current = self.total;
+        self.current = match self.total {
+            Some(total) => current.min(total),
+            None => current,
+        };
+        self.redraw();
+    }
+
+    // Redraw without changing the position, e.g. to advance the spinner frame
+    pub fn tick(&mut self) {
+        if !self.state.is_done() {
+            self.redraw();
+        }
+    }
+
+    // Redraw from a background thread every `interval`, so a spinner, elapsed
+    // time and ETA keep moving while updates are rare or the caller is blocked.
+    // Stops when the bar finishes.
+    pub fn enable_steady_tick(this: &Arc<Mutex<Self>>, interval: Duration) {
+        let Ok(mut bar) = this.lock() else {
+            return;
+        };
+        bar.stop_ticker();
+        if bar.state.is_done() {
+            return;
+        }
+
+        let stop = Arc::new(AtomicBool::new(false));
+        bar.ticker = Some(stop.clone());
+        let weak: Weak<Mutex<Self>> = Arc::downgrade(this);
+        std::thread::spawn(move || {
+            loop {
+                std::thread::sleep(interval);
+                if stop.load(Ordering::Relaxed) {
+                    break;
+                }
+                let Some(bar) = weak.upgrade() else {
+                    break;
+                };
+                if let Ok(mut bar) = bar.lock() {
+                    bar.tick();
+                };
+            }
+        });
+    }
+
+    pub fn disable_steady_tick(&mut self) {
+        self.stop_ticker();
+    }
+
+    fn stop_ticker(&mut self) {
+        if let Some(stop) = self.ticker.take() {
+            stop.store(true, Ordering::Relaxed);
         }
+    }
+
+    // Continue a job that already got to `position` in an earlier run taking
+    // `elapsed`. Elapsed time counts from the earlier run and the rate starts
+    // from its average, so the ETA is sensible right away.
+    pub fn resume_from(&mut self, position: u64, elapsed: Duration) {
+        let now = Instant::now();
+        self.start_time = now.checked_sub(elapsed).unwrap_or(self.start_time);
+        self.paused_at = self.paused_at.map(|_| now);
+        self.paused_total = Duration::ZERO;
+        self.suspend_offset = suspend::suspended_total(now);
+        self.current = match self.total {
+            Some(total) => position.min(total),
+            None => position,
+        };
+        let rate = (!elapsed.is_zero()).then(|| self.current as f64 / elapsed.as_secs_f64());
+        self.estimator.seed(self.current, rate, self.active_elapsed(now));
         self.redraw();
     }
 
+    // Hide the bar and stop its clock, e.g. while asking the user something.
+    // Updates are still counted but not drawn until `resume`.
+    pub fn pause(&mut self) {
+        if self.paused_at.is_some() || self.state.is_done() {
+            return;
+        }
+        self.paused_at = Some(Instant::now());
+
+        if self.callback.is_some() || self.parent.is_some() {
+            return;
+        }
+        match &self.group {
+            Some(group) => group.update(self.owner, Vec::new(), false),
+            None => term::remove_live(self.owner),
+        }
+    }
+
+    // Show the bar again; the paused time is left out of elapsed time and ETA
+    pub fn resume(&mut self) {
+        if let Some(paused_at) = self.paused_at.take() {
+            self.paused_total += paused_at.elapsed();
+            self.draw(Instant::now());
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    // Run `f` with this bar paused, then draw it again
+    pub fn suspend<R>(&mut self, f: impl FnOnce() -> R) -> R {
+        self.pause();
+        let result = f();
+        self.resume();
+        result
+    }
+
+    // Time the bar has been running, not counting pauses and suspensions
+    fn active_elapsed(&self, now: Instant) -> Duration {
+        let paused = self.paused_total + self.paused_at.map_or(Duration::ZERO, |at| now.saturating_duration_since(at));
+        let suspended = suspend::suspended_total(now).saturating_sub(self.suspend_offset);
+        now.saturating_duration_since(self.start_time).saturating_sub(paused + suspended)
+    }
+
+    // How long older samples keep influencing the rate (default 3s); shorter
+    // reacts faster to changes in throughput, longer gives a steadier ETA
+    pub fn set_rate_smoothing(&mut self, smoothing: Duration) {
+        self.estimator.set_smoothing(smoothing);
+    }
+
+    // Draw at most `hz` times a second (default 15); 0 draws on every change.
+    // Changes in between are skipped, the final state is always drawn.
+    pub fn set_draw_rate(&mut self, hz: u32) {
+        self.draw_interval = match hz {
+            0 => Duration::ZERO,
+            hz => Duration::from_secs(1) / hz,
+        };
+    }
+
     pub fn increment(&mut self, amount: u64) {
-        self.update(self.current + amount);
+        self.update(self.current.saturating_add(amount));
+    }
+
+    // Finish, keeping the current message
+    pub fn finish(&mut self) {
+        let message = std::mem::take(&mut self.message);
+        self.finish_with_message(&message);
     }
 
     pub fn finish_with_message(&mut self, message: &str) {
-        self.current = self.total;
+        // A spinner keeps its look and simply shows a check mark
+        if let Some(total) = self.total {
+            self.current = total;
+        }
+        self.end(ProgressState::Finished, message);
+    }
+
+    // Stop where the bar is, marked as not completed
+    pub fn abandon_with_message(&mut self, message: &str) {
+        self.end(ProgressState::Abandoned, message);
+    }
+
+    // Stop where the bar is, marked as cancelled
+    pub fn cancel_with_message(&mut self, message: &str) {
+        self.end(ProgressState::Cancelled, message);
+    }
+
+    pub fn state(&self) -> ProgressState {
+        self.state
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.state.is_done()
+    }
+
+    fn end(&mut self, state: ProgressState, message: &str) {
+        self.stop_ticker();
+        if let Some(paused_at) = self.paused_at.take() {
+            self.paused_total += paused_at.elapsed();
+        }
         self.message = message.to_string();
-        self.finished = true;
+        self.state = state;
         self.redraw();
-        println!();
-    }
-
-    fn redraw(&self) {
-        let percent = if self.total == 0 { 100 } else { (self.current as f64 / self.total as f64 * 100.0) as u8 };
-        let filled_width = (self.width as f64 * (percent as f64 / 100.0)) as usize;
-        
-        let elapsed = self.start_time.elapsed().as_secs_f64();
-        let speed = if elapsed > 0.0 { self.current as f64 / elapsed } else { 0.0 };
-        let eta = if speed > 0.0 && self.current < self.total {
-            (self.total - self.current) as f64 / speed
-        } else {
-            0.0
-        };
+        self.emit_event(state.event_kind());
+    }
+
+    fn emit_event(&self, kind: ProgressEventKind) {
+        if events::is_active() {
+            events::emit(&self.event(kind));
+        }
+    }
+
+    fn event(&self, kind: ProgressEventKind) -> ProgressEvent {
+        ProgressEvent {
+            event: kind,
+            id: self.owner,
+            pos: self.current,
+            total: self.total,
+            rate: self.estimator.rate(),
+            eta: self.total.and_then(|total| self.estimator.eta(self.current, total)).map(|eta| eta.as_secs_f64()),
+            elapsed: self.active_elapsed(Instant::now()).as_secs_f64(),
+            message: self.message.clone(),
+        }
+    }
+
+    // Hand every draw of this bar to `callback` instead of the terminal, e.g. to
+    // show progress in the host's own UI. Runs with the bar locked, so it must
+    // not call back into this bar.
+    pub fn set_draw_callback<F: FnMut(&ProgressEvent) + Send + 'static>(&mut self, callback: F) {
+        if self.callback.is_none() {
+            self.clear_live();
+        }
+        self.callback = Some(Box::new(callback));
+    }
+
+    // Go back to drawing on the terminal
+    pub fn clear_draw_callback(&mut self) {
+        self.callback = None;
+    }
+
+    // Take this bar's lines off the screen, e.g. before it is drawn elsewhere
+    fn clear_live(&self) {
+        if self.parent.is_none() && self.group.is_none() {
+            term::remove_live(self.owner);
+        }
+    }
 
-        let bar = format!(
-            "{} {}% [{}{}>{}] {}/{} [{:.2}/s, ETA: {:.1}s] {}",
-            if self.finished { "✓".green() } else { "•".bright_blue() },
-            percent,
-            "=".repeat(filled_width),
-            if filled_width < self.width { ">" } else { "" },
-            " ".repeat(self.width - filled_width.min(self.width)),
-            self.current,
-            self.total,
-            speed,
+    // Draw the bar unless it was drawn less than `draw_interval` ago. Skipped
+    // changes show up with the next draw, or the next tick of a steady ticker.
+    fn redraw(&mut self) {
+        let now = Instant::now();
+        self.estimator.record(self.current, self.active_elapsed(now));
+        if !self.state.is_done() {
+            if let Some(last_draw) = self.last_draw {
+                if now.duration_since(last_draw) < self.draw_interval {
+                    return;
+                }
+            }
+        }
+        self.draw(now);
+    }
+
+    fn draw(&mut self, now: Instant) {
+        if self.paused_at.is_some() {
+            return;
+        }
+        self.last_draw = Some(now);
+        if !self.state.is_done() {
+            self.emit_event(ProgressEventKind::Updated);
+        }
+        if self.callback.is_some() {
+            let event = self.event(self.state.event_kind());
+            if let Some(callback) = self.callback.as_mut() {
+                callback(&event);
+            }
+            return;
+        }
+
+        let elapsed = self.active_elapsed(now);
+        let speed = self.estimator.rate();
+        let eta = self.total.and_then(|total| self.estimator.eta(self.current, total));
+
+        let state = RenderState {
+            pos: self.current,
+            len: self.total,
+            elapsed,
+            rate: speed,
             eta,
-            self.message
-        );
+            message: &self.message,
+            prefix: &self.prefix,
+            state: self.state,
+        };
 
-        if let Ok(mut stdout) = self.stdout.lock() {
-            let _ = write!(stdout, "\r{}", bar);
-            let _ = stdout.flush();
+        let stream = match &self.group {
+            Some(group) => group.stream(),
+            None => self.target.stream(),
+        };
+        let width = term::terminal_width(stream);
+        if let Some(parent) = &self.parent {
+            let line = format!("{}{}", CHILD_INDENT, self.style.render(&state, width.saturating_sub(CHILD_INDENT.len()), should_colorize(stream)));
+            let (owner, fraction, done) = (self.owner, self.fraction(), self.state.is_done());
+            if let Ok(mut parent) = parent.lock() {
+                parent.child_changed(owner, fraction, line, done);
+            }
+            return;
+        }
+
+        let mode = match &self.target {
+            ProgressTarget::Hidden => ProgressMode::Hidden,
+            // A writer is never a terminal to redraw on
+            ProgressTarget::Writer(_) if progress_mode() != ProgressMode::Hidden => ProgressMode::Plain,
+            _ => progress_mode().resolve(stream),
+        };
+        match mode {
+            ProgressMode::Hidden => {}
+            ProgressMode::Plain => {
+                // No cursor movement: a status line now and then, and always the final one
+                let due = self.last_plain.is_none_or(|last| now.duration_since(last) >= plain_interval());
+                if due || self.state.is_done() {
+                    self.last_plain = Some(now);
+                    let color = self.target.colors(should_colorize(stream));
+                    let line = self.style.render_plain(&state, color);
+                    match &self.target {
+                        ProgressTarget::Writer(writer) => {
+                            if let Ok(mut writer) = writer.lock() {
+                                let _ = writeln!(writer, "{}", line);
+                                let _ = writer.flush();
+                            }
+                        }
+                        _ => term::print_line_to(stream, &line),
+                    }
+                }
+            }
+            _ => {
+                let mut lines = vec![self.style.render(&state, width, should_colorize(stream))];
+                if !self.state.is_done() {
+                    if let Some(line) = self.children.as_ref().and_then(|children| children.active_line()) {
+                        lines.push(line.to_string());
+                    }
+                }
+                match &self.group {
+                    Some(group) => group.update(self.owner, lines, self.state.is_done()),
+                    None if self.state.is_done() => term::finish_live(self.owner, stream, lines),
+                    None => term::set_live(self.owner, stream, lines),
+                }
+            }
         }
     }
 }
 
-type ProgressBarStorage = Vec<Arc<Mutex<ProgressBar>>>;
-static PROGRESS_BARS: once_cell::sync::Lazy<Mutex<ProgressBarStorage>> = 
-    once_cell::sync::Lazy::new(|| Mutex::new(Vec::new()));
-static NEXT_ID: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+impl Drop for ProgressBar {
+    // A bar that goes away while running is left on screen as abandoned,
+    // rather than as a live line nobody will ever update again
+    fn drop(&mut self) {
+        if !self.state.is_done() {
+            let message = std::mem::take(&mut self.message);
+            self.abandon_with_message(&message);
+        }
+    }
+}
+
+// Bars that are done stay reachable by id until freed, but only this many;
+// beyond that the oldest are released so callers that never free don't leak
+const MAX_DONE_BARS: usize = 64;
+
+type ProgressBarStorage = HashMap<usize, Arc<Mutex<ProgressBar>>>;
+static PROGRESS_BARS: once_cell::sync::Lazy<Mutex<ProgressBarStorage>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
+
+// The map stays consistent even if a thread panicked while holding it, so a
+// poisoned lock is recovered rather than making every id look dead
+fn registry() -> MutexGuard<'static, ProgressBarStorage> {
+    PROGRESS_BARS.lock().unwrap_or_else(PoisonError::into_inner)
+}
 
 pub fn create_progress_bar(total: u64) -> usize {
-    let id = NEXT_ID.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-    let progress = ProgressBar::new(total);
-    
-    if let Ok(mut bars) = PROGRESS_BARS.lock() {
-        bars.push(progress);
+    register_progress_bar(ProgressBar::new(total))
+}
+
+pub fn create_bytes_progress_bar(total: u64) -> usize {
+    register_progress_bar(ProgressBar::new_bytes(total))
+}
+
+pub fn create_parent_progress() -> usize {
+    register_progress_bar(ProgressBar::new_parent())
+}
+
+// Add a weighted sub-task to the parent bar `parent_id`; returns the child's bar id
+pub fn add_child_progress(parent_id: usize, weight: f64, total: u64) -> Option<usize> {
+    let parent = get_progress_bar(parent_id)?;
+    Some(register_progress_bar(ProgressBar::add_child(&parent, weight, total)))
+}
+
+pub fn create_spinner() -> usize {
+    register_progress_bar(ProgressBar::new_spinner())
+}
+
+fn register_progress_bar(progress: Arc<Mutex<ProgressBar>>) -> usize {
+    // The bar's own id, so ids in progress events match those given out here
+    let Ok(id) = progress.lock().map(|bar| bar.id() as usize) else {
+        return usize::MAX;
+    };
+
+    // Evicted bars are dropped after the registry lock is released
+    let mut evicted = Vec::new();
+    {
+        let mut bars = registry();
+        let mut done: Vec<usize> = bars
+            .iter()
+            .filter(|(_, bar)| bar.try_lock().is_ok_and(|bar| bar.state.is_done()))
+            .map(|(&id, _)| id)
+            .collect();
+        if done.len() >= MAX_DONE_BARS {
+            done.sort_unstable();
+            for old in &done[..=done.len() - MAX_DONE_BARS] {
+                evicted.extend(bars.remove(old));
+            }
+        }
+        bars.insert(id, progress);
     }
+    drop(evicted);
 
     id
 }
 
+// Release the bar `id`; a bar still running is abandoned once nothing else holds it
+pub fn free_progress(id: usize) -> bool {
+    let removed = registry().remove(&id);
+    removed.is_some()
+}
+
 pub fn update_progress(id: usize, current: u64, message: Option<&str>) -> bool {
     if let Some(progress) = get_progress_bar(id) {
         if let Ok(mut bar) = progress.lock() {
-            bar.update(current);
+            // One draw for both changes
             if let Some(msg) = message {
-                bar.set_message(msg);
+                bar.replace_message(msg);
             }
+            bar.update(current);
             return true;
         }
     }
@@ -125,11 +645,205 @@ pub fn finish_progress(id: usize, message: Option<&str>) -> bool {
     false
 }
 
+pub fn abandon_progress(id: usize, message: Option<&str>) -> bool {
+    if let Some(progress) = get_progress_bar(id) {
+        if let Ok(mut bar) = progress.lock() {
+            bar.abandon_with_message(message.unwrap_or("中断"));
+            return true;
+        }
+    }
+    false
+}
+
+pub fn cancel_progress(id: usize, message: Option<&str>) -> bool {
+    if let Some(progress) = get_progress_bar(id) {
+        if let Ok(mut bar) = progress.lock() {
+            bar.cancel_with_message(message.unwrap_or("キャンセルされました"));
+            return true;
+        }
+    }
+    false
+}
+
 fn get_progress_bar(id: usize) -> Option<Arc<Mutex<ProgressBar>>> {
-    if let Ok(bars) = PROGRESS_BARS.lock() {
-        if id < bars.len() {
-            return Some(bars[id].clone());
+    registry().get(&id).cloned()
+}
+
+// Apply `f` to the bar's style; false if the bar does not exist or `f` fails
+pub fn restyle_progress<F>(id: usize, f: F) -> bool
+where
+    F: FnOnce(ProgressStyle) -> Result<ProgressStyle, crate::errors::CliError>,
+{
+    if let Some(progress) = get_progress_bar(id) {
+        if let Ok(mut bar) = progress.lock() {
+            if let Ok(style) = f(bar.style.clone()) {
+                bar.style = style;
+                return true;
+            }
+        }
+    }
+    false
+}
+
+pub fn set_progress_draw_rate(id: usize, hz: u32) -> bool {
+    if let Some(progress) = get_progress_bar(id) {
+        if let Ok(mut bar) = progress.lock() {
+            bar.set_draw_rate(hz);
+            return true;
+        }
+    }
+    false
+}
+
+pub fn resume_progress_from(id: usize, position: u64, elapsed: Duration) -> bool {
+    if let Some(progress) = get_progress_bar(id) {
+        if let Ok(mut bar) = progress.lock() {
+            bar.resume_from(position, elapsed);
+            return true;
+        }
+    }
+    false
+}
+
+pub fn pause_progress(id: usize) -> bool {
+    if let Some(progress) = get_progress_bar(id) {
+        if let Ok(mut bar) = progress.lock() {
+            bar.pause();
+            return true;
         }
     }
-    None
-}
\ No newline at end of file
+    false
+}
+
+pub fn resume_progress(id: usize) -> bool {
+    if let Some(progress) = get_progress_bar(id) {
+        if let Ok(mut bar) = progress.lock() {
+            bar.resume();
+            return true;
+        }
+    }
+    false
+}
+
+pub fn set_progress_length(id: usize, total: u64) -> bool {
+    if let Some(progress) = get_progress_bar(id) {
+        if let Ok(mut bar) = progress.lock() {
+            bar.set_length(total);
+            return true;
+        }
+    }
+    false
+}
+
+// An interval of zero stops the ticker
+pub fn set_progress_steady_tick(id: usize, interval: Duration) -> bool {
+    let Some(progress) = get_progress_bar(id) else {
+        return false;
+    };
+    if interval.is_zero() {
+        if let Ok(mut bar) = progress.lock() {
+            bar.disable_steady_tick();
+        }
+    } else {
+        ProgressBar::enable_steady_tick(&progress, interval);
+    }
+    true
+}
+
+pub fn set_progress_target(id: usize, target: ProgressTarget) -> bool {
+    if let Some(progress) = get_progress_bar(id) {
+        if let Ok(mut bar) = progress.lock() {
+            bar.set_draw_target(target);
+            return true;
+        }
+    }
+    false
+}
+
+// Plain status lines to a file descriptor that stays owned by the caller
+#[cfg(unix)]
+pub(crate) fn set_progress_target_fd(id: usize, fd: i32) -> bool {
+    set_progress_target(id, ProgressTarget::writer(unsafe { io::FdWriter::new(fd) }))
+}
+
+pub fn set_progress_prefix(id: usize, prefix: &str) -> bool {
+    if let Some(progress) = get_progress_bar(id) {
+        if let Ok(mut bar) = progress.lock() {
+            bar.set_prefix(prefix);
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry_survives_a_poisoned_lock() {
+        let _ = std::thread::spawn(|| {
+            let _bars = registry();
+            panic!("poison the progress registry");
+        })
+        .join();
+        assert!(PROGRESS_BARS.is_poisoned());
+
+        let id = create_spinner();
+        assert_ne!(id, usize::MAX);
+        assert!(get_progress_bar(id).is_some());
+        assert!(free_progress(id));
+    }
+
+    #[test]
+    fn writer_targets_are_never_colored() {
+        assert!(!ProgressTarget::writer(Vec::new()).colors(true));
+        assert!(!ProgressTarget::writer(Vec::new()).colors(false));
+        assert!(ProgressTarget::Stderr.colors(true));
+        assert!(!ProgressTarget::Stdout.colors(false));
+    }
+
+    #[test]
+    fn plain_lines_are_colored_only_on_request() {
+        let style = ProgressStyle::new().with_plain_template("{icon} {pos}/{len}").unwrap();
+        let state = RenderState {
+            pos: 10,
+            len: Some(10),
+            elapsed: Duration::ZERO,
+            rate: 0.0,
+            eta: None,
+            message: "",
+            prefix: "",
+            state: ProgressState::Finished,
+        };
+        assert!(style.render_plain(&state, true).contains('\x1b'));
+        assert_eq!(style.render_plain(&state, false), "✓ 10/10");
+    }
+
+    #[test]
+    fn writer_receives_the_final_plain_line() {
+        struct Shared(Arc<Mutex<Vec<u8>>>);
+        impl std::io::Write for Shared {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let bar = ProgressBar::new(10);
+        {
+            let mut bar = bar.lock().unwrap();
+            bar.set_draw_target(ProgressTarget::writer(Shared(output.clone())));
+            bar.set_style(ProgressStyle::new().with_plain_template("{icon} {bar} {pos}/{len}").unwrap());
+            bar.finish();
+        }
+
+        let written = String::from_utf8(output.lock().unwrap().clone()).unwrap();
+        assert!(written.contains("10/10"));
+        assert!(!written.contains('\x1b'));
+    }
+}
@@ -0,0 +1,67 @@
+use std::ffi::{CString, c_void};
+use std::os::raw::c_char;
+
+use super::events::UserData;
+use super::{ProgressEventKind, get_progress_bar};
+
+// Bar state handed to a C draw callback
+#[repr(C)]
+pub struct CliProgressState {
+    pub id: u64,
+    pub current: u64,
+    // Meaningless while has_total is false (spinners)
+    pub total: u64,
+    pub has_total: bool,
+    // Items per second
+    pub rate: f64,
+    // Seconds left, or -1 when unknown
+    pub eta: f64,
+    pub elapsed: f64,
+    // 0 = running, 1 = finished, 2 = abandoned, 3 = cancelled
+    pub state: i32,
+    // Valid only during the callback
+    pub message: *const c_char,
+}
+
+pub type CliProgressCallback = extern "C" fn(state: *const CliProgressState, user_data: *mut c_void);
+
+// Draw the bar `id` through `callback` instead of the terminal; NULL goes back
+// to terminal drawing. The callback must not call progress functions for the same bar.
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_set_progress_callback(id: usize, callback: Option<CliProgressCallback>, user_data: *mut c_void) -> bool {
+    let Some(progress) = get_progress_bar(id) else {
+        return false;
+    };
+    let Ok(mut bar) = progress.lock() else {
+        return false;
+    };
+
+    let Some(callback) = callback else {
+        bar.clear_draw_callback();
+        return true;
+    };
+
+    let user_data = UserData(user_data);
+    bar.set_draw_callback(move |event| {
+        // Interior NUL bytes would cut the message short anyway
+        let message = CString::new(event.message.replace('\0', "")).unwrap_or_default();
+        let state = CliProgressState {
+            id: event.id,
+            current: event.pos,
+            total: event.total.unwrap_or(0),
+            has_total: event.total.is_some(),
+            rate: event.rate,
+            eta: event.eta.unwrap_or(-1.0),
+            elapsed: event.elapsed,
+            state: match event.event {
+                ProgressEventKind::Finished => 1,
+                ProgressEventKind::Abandoned => 2,
+                ProgressEventKind::Cancelled => 3,
+                _ => 0,
+            },
+            message: message.as_ptr(),
+        };
+        callback(&state, user_data.get());
+    });
+    true
+}
@@ -0,0 +1,151 @@
+use std::time::Duration;
+
+// Samples closer together than this are merged, tiny intervals make the rate jumpy
+const MIN_SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+// Time constant of the moving average: older samples fade out over roughly this long
+const DEFAULT_SMOOTHING: Duration = Duration::from_secs(3);
+
+// Exponentially weighted moving average of progress per second.
+//
+// Unlike `current / elapsed`, the estimate follows changes in throughput, so a
+// slow start does not drag the ETA down for the rest of the run. Times are the
+// bar's active time, so paused periods don't count as a stall.
+#[derive(Debug, Clone)]
+pub(super) struct RateEstimator {
+    rate: Option<f64>,
+    smoothing: Duration,
+    last_pos: u64,
+    last_time: Duration,
+}
+
+impl RateEstimator {
+    pub fn new(now: Duration) -> Self {
+        RateEstimator {
+            rate: None,
+            smoothing: DEFAULT_SMOOTHING,
+            last_pos: 0,
+            last_time: now,
+        }
+    }
+
+    pub fn set_smoothing(&mut self, smoothing: Duration) {
+        self.smoothing = smoothing.max(MIN_SAMPLE_INTERVAL);
+    }
+
+    // Start counting from `pos` without treating the jump as throughput,
+    // optionally with the rate seen before, e.g. when resuming a job
+    pub fn seed(&mut self, pos: u64, rate: Option<f64>, now: Duration) {
+        self.last_pos = pos;
+        self.last_time = now;
+        if let Some(rate) = rate.filter(|rate| rate.is_finite() && *rate > 0.0) {
+            self.rate = Some(rate);
+        }
+    }
+
+    pub fn record(&mut self, pos: u64, now: Duration) {
+        if pos < self.last_pos {
+            // Moved backwards: measure from the new position
+            self.seed(pos, None, now);
+            return;
+        }
+
+        let dt = now.saturating_sub(self.last_time);
+        if dt < MIN_SAMPLE_INTERVAL {
+            return;
+        }
+
+        let instant_rate = (pos - self.last_pos) as f64 / dt.as_secs_f64();
+        let alpha = 1.0 - (-dt.as_secs_f64() / self.smoothing.as_secs_f64()).exp();
+        self.rate = Some(match self.rate {
+            Some(rate) => rate + alpha * (instant_rate - rate),
+            None => instant_rate,
+        });
+        self.last_pos = pos;
+        self.last_time = now;
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate.unwrap_or(0.0)
+    }
+
+    // None when the remaining time is unknown, including a rate so close to
+    // zero after a long stall that the estimate no longer fits in a Duration
+    pub fn eta(&self, pos: u64, total: u64) -> Option<Duration> {
+        let rate = self.rate?;
+        if rate <= 0.0 || pos >= total {
+            return None;
+        }
+        Duration::try_from_secs_f64((total - pos) as f64 / rate).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn no_rate_until_first_sample() {
+        let estimator = RateEstimator::new(Duration::ZERO);
+        assert_eq!(estimator.rate(), 0.0);
+        assert_eq!(estimator.eta(0, 100), None);
+    }
+
+    #[test]
+    fn steady_progress_gives_steady_eta() {
+        let mut estimator = RateEstimator::new(Duration::ZERO);
+        for i in 1..=10 {
+            estimator.record(i * 10, secs(i));
+        }
+        assert!((estimator.rate() - 10.0).abs() < 1e-9);
+        assert_eq!(estimator.eta(100, 200), Some(secs(10)));
+        assert_eq!(estimator.eta(200, 200), None);
+    }
+
+    #[test]
+    fn close_samples_are_merged() {
+        let mut estimator = RateEstimator::new(Duration::ZERO);
+        estimator.record(5, Duration::from_millis(10));
+        assert_eq!(estimator.rate(), 0.0);
+        estimator.record(10, secs(1));
+        assert!((estimator.rate() - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn moving_backwards_restarts_measurement() {
+        let mut estimator = RateEstimator::new(Duration::ZERO);
+        estimator.record(100, secs(1));
+        estimator.record(10, secs(2));
+        estimator.record(20, secs(3));
+        // The drop from 100 to 10 isn't counted as negative throughput
+        assert!(estimator.rate() > 0.0);
+    }
+
+    #[test]
+    fn seed_ignores_unusable_rates() {
+        let mut estimator = RateEstimator::new(Duration::ZERO);
+        estimator.seed(50, Some(5.0), secs(10));
+        for rate in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            estimator.seed(50, Some(rate), secs(10));
+            assert_eq!(estimator.rate(), 5.0);
+        }
+        assert_eq!(estimator.eta(50, 100), Some(secs(10)));
+    }
+
+    #[test]
+    fn long_stall_makes_eta_unknown_instead_of_overflowing() {
+        let mut estimator = RateEstimator::new(Duration::ZERO);
+        estimator.seed(1, Some(1.0 / (1u64 << 62) as f64), Duration::ZERO);
+        assert_eq!(estimator.eta(1, u64::MAX), None);
+
+        let mut estimator = RateEstimator::new(Duration::ZERO);
+        estimator.record(10, secs(1));
+        for i in 2..10_000 {
+            estimator.record(10, secs(i));
+        }
+        assert_eq!(estimator.eta(10, u64::MAX), None);
+    }
+}
@@ -0,0 +1,150 @@
+use serde::Serialize;
+use std::ffi::{CString, c_void};
+use std::io::Write;
+use std::os::raw::c_char;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(unix)]
+use super::io::FdWriter;
+
+// What happened to a bar
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProgressEventKind {
+    Created,
+    Updated,
+    Message,
+    Finished,
+    Abandoned,
+    Cancelled,
+}
+
+// One progress event, serialized as a JSON line such as
+// {"event":"updated","id":3,"pos":420,"total":1000,"rate":35.2,"eta":16.5,"elapsed":12.0,"message":""}
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgressEvent {
+    pub event: ProgressEventKind,
+    // Same as the id returned by `create_progress_bar` and `ProgressBar::id`
+    pub id: u64,
+    // For a parent bar, pos and total are thousandths of the overall progress (`PARENT_SCALE`)
+    pub pos: u64,
+    // null while the total is unknown
+    pub total: Option<u64>,
+    // Items per second
+    pub rate: f64,
+    // Seconds; null when unknown
+    pub eta: Option<f64>,
+    pub elapsed: f64,
+    pub message: String,
+}
+
+impl ProgressEvent {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+enum EventSink {
+    Writer(Box<dyn Write + Send>),
+    Callback(Box<dyn Fn(&ProgressEvent) + Send>),
+}
+
+static SINKS: Mutex<Vec<EventSink>> = Mutex::new(Vec::new());
+// Lets bars skip building events while nobody listens
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+// Write every progress event as a JSON line to `writer`. Terminal drawing is
+// unaffected; combine with `ProgressMode::Hidden` to get events only.
+pub fn add_progress_event_writer<W: Write + Send + 'static>(writer: W) {
+    add_sink(EventSink::Writer(Box::new(writer)));
+}
+
+// Call `callback` with every progress event. It runs while the bar is locked,
+// so it must not call back into the progress API.
+pub fn add_progress_event_callback<F: Fn(&ProgressEvent) + Send + 'static>(callback: F) {
+    add_sink(EventSink::Callback(Box::new(callback)));
+}
+
+pub fn clear_progress_event_sinks() {
+    if let Ok(mut sinks) = SINKS.lock() {
+        sinks.clear();
+        ACTIVE.store(false, Ordering::Relaxed);
+    }
+}
+
+fn add_sink(sink: EventSink) {
+    if let Ok(mut sinks) = SINKS.lock() {
+        sinks.push(sink);
+        ACTIVE.store(true, Ordering::Relaxed);
+    }
+}
+
+pub(super) fn is_active() -> bool {
+    ACTIVE.load(Ordering::Relaxed)
+}
+
+pub(super) fn emit(event: &ProgressEvent) {
+    let Ok(mut sinks) = SINKS.lock() else {
+        return;
+    };
+
+    let mut json = None;
+    for sink in sinks.iter_mut() {
+        match sink {
+            EventSink::Writer(writer) => {
+                let line = json.get_or_insert_with(|| event.to_json());
+                let _ = writeln!(writer, "{}", line);
+                let _ = writer.flush();
+            }
+            EventSink::Callback(callback) => callback(event),
+        }
+    }
+}
+
+// C interface
+
+// Write JSON line events to the open file descriptor `fd`, which stays owned by the caller
+#[cfg(unix)]
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_progress_events_to_fd(fd: i32) -> bool {
+    if fd < 0 {
+        return false;
+    }
+    add_progress_event_writer(unsafe { FdWriter::new(fd) });
+    true
+}
+
+pub type CliProgressEventCallback = extern "C" fn(json: *const c_char, user_data: *mut c_void);
+
+pub(super) struct UserData(pub(super) *mut c_void);
+
+// The pointer is only handed back to the caller's callback
+unsafe impl Send for UserData {}
+
+impl UserData {
+    pub(super) fn get(&self) -> *mut c_void {
+        self.0
+    }
+}
+
+// Call `callback` with each event as a JSON string, valid only during the call
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_progress_events_to_callback(callback: Option<CliProgressEventCallback>, user_data: *mut c_void) -> bool {
+    let Some(callback) = callback else {
+        return false;
+    };
+
+    let user_data = UserData(user_data);
+    add_progress_event_callback(move |event| {
+        if let Ok(json) = CString::new(event.to_json()) {
+            callback(json.as_ptr(), user_data.get());
+        }
+    });
+    true
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_clear_progress_event_sinks() {
+    clear_progress_event_sinks();
+}
@@ -0,0 +1,175 @@
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex};
+
+use super::ProgressBar;
+
+// A reader that advances `bar` by every byte read through it
+pub struct ProgressReader<R> {
+    inner: R,
+    bar: Arc<Mutex<ProgressBar>>,
+}
+
+impl<R: Read> ProgressReader<R> {
+    pub fn new(inner: R, bar: Arc<Mutex<ProgressBar>>) -> Self {
+        ProgressReader { inner, bar }
+    }
+
+    pub fn bar(&self) -> &Arc<Mutex<ProgressBar>> {
+        &self.bar
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        advance(&self.bar, read);
+        Ok(read)
+    }
+}
+
+// A writer that advances `bar` by every byte written through it
+pub struct ProgressWriter<W> {
+    inner: W,
+    bar: Arc<Mutex<ProgressBar>>,
+}
+
+impl<W: Write> ProgressWriter<W> {
+    pub fn new(inner: W, bar: Arc<Mutex<ProgressBar>>) -> Self {
+        ProgressWriter { inner, bar }
+    }
+
+    pub fn bar(&self) -> &Arc<Mutex<ProgressBar>> {
+        &self.bar
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for ProgressWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        advance(&self.bar, written);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn advance(bar: &Arc<Mutex<ProgressBar>>, bytes: usize) {
+    if bytes == 0 {
+        return;
+    }
+    if let Ok(mut bar) = bar.lock() {
+        bar.increment(bytes as u64);
+    }
+}
+
+// Writes to a file descriptor owned by a C caller, without closing it on drop
+#[cfg(unix)]
+pub(super) struct FdWriter(std::mem::ManuallyDrop<std::fs::File>);
+
+#[cfg(unix)]
+impl FdWriter {
+    // `fd` must stay open for as long as the writer is in use
+    pub(super) unsafe fn new(fd: i32) -> Self {
+        use std::os::fd::FromRawFd;
+        FdWriter(std::mem::ManuallyDrop::new(unsafe { std::fs::File::from_raw_fd(fd) }))
+    }
+}
+
+#[cfg(unix)]
+impl Write for FdWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::ProgressTarget;
+
+    fn hidden_bar(total: u64) -> Arc<Mutex<ProgressBar>> {
+        let bar = ProgressBar::new_bytes(total);
+        bar.lock().unwrap().set_draw_target(ProgressTarget::Hidden);
+        bar
+    }
+
+    fn position(bar: &Arc<Mutex<ProgressBar>>) -> u64 {
+        bar.lock().unwrap().current
+    }
+
+    #[test]
+    fn reader_counts_the_bytes_read() {
+        let bar = hidden_bar(10);
+        let mut reader = ProgressBar::wrap_read(&bar, &b"0123456789"[..]);
+
+        let mut buf = [0; 4];
+        assert_eq!(reader.read(&mut buf).unwrap(), 4);
+        assert_eq!(position(&bar), 4);
+
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"456789");
+        assert_eq!(position(&bar), 10);
+        // Reads at the end add nothing
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+        assert_eq!(position(&bar), 10);
+    }
+
+    #[test]
+    fn writer_counts_the_bytes_written() {
+        let bar = hidden_bar(8);
+        let mut writer = ProgressBar::wrap_write(&bar, Vec::new());
+
+        writer.write_all(b"abc").unwrap();
+        assert_eq!(position(&bar), 3);
+        writer.write_all(b"defgh").unwrap();
+        writer.flush().unwrap();
+        assert_eq!(position(&bar), 8);
+        assert_eq!(writer.into_inner(), b"abcdefgh");
+    }
+
+    #[test]
+    fn short_writes_count_only_what_was_accepted() {
+        struct TwoAtATime(Vec<u8>);
+        impl Write for TwoAtATime {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                let n = buf.len().min(2);
+                self.0.extend_from_slice(&buf[..n]);
+                Ok(n)
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let bar = hidden_bar(5);
+        let mut writer = ProgressWriter::new(TwoAtATime(Vec::new()), bar.clone());
+        assert_eq!(writer.write(b"hello").unwrap(), 2);
+        assert_eq!(position(&bar), 2);
+        writer.write_all(b"llo").unwrap();
+        assert_eq!(position(&bar), 5);
+    }
+
+    #[test]
+    fn increments_saturate_instead_of_overflowing() {
+        let bar = hidden_bar(10);
+        let mut bar = bar.lock().unwrap();
+        bar.increment(3);
+        bar.increment(u64::MAX);
+        assert_eq!(bar.current, 10);
+    }
+}
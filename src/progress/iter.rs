@@ -0,0 +1,195 @@
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use super::ProgressBar;
+
+// An iterator that advances a progress bar for every item and finishes it
+// when exhausted. Dropping it early leaves the bar abandoned.
+pub struct ProgressIter<I> {
+    inner: I,
+    bar: Arc<Mutex<ProgressBar>>,
+}
+
+impl<I> ProgressIter<I> {
+    pub fn bar(&self) -> &Arc<Mutex<ProgressBar>> {
+        &self.bar
+    }
+}
+
+impl<I: Iterator> Iterator for ProgressIter<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.inner.next();
+        if let Ok(mut bar) = self.bar.lock() {
+            match item {
+                Some(_) => bar.increment(1),
+                None if !bar.is_finished() => bar.finish(),
+                None => {}
+            }
+        }
+        item
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<I: ExactSizeIterator> ExactSizeIterator for ProgressIter<I> {}
+
+// `.progress()` on any iterator: a bar sized from the iterator's length when it
+// is known exactly, a spinner otherwise
+pub trait ProgressIterator: Iterator + Sized {
+    fn progress(self) -> ProgressIter<Self> {
+        let bar = match self.size_hint() {
+            (lower, Some(upper)) if lower == upper => ProgressBar::new(lower as u64),
+            _ => ProgressBar::new_spinner(),
+        };
+        self.progress_with(bar)
+    }
+
+    // For iterators whose length is known but not reported by `size_hint`
+    fn progress_count(self, len: u64) -> ProgressIter<Self> {
+        self.progress_with(ProgressBar::new(len))
+    }
+
+    // Drive an existing bar, e.g. one styled beforehand or part of a MultiProgress
+    fn progress_with(self, bar: Arc<Mutex<ProgressBar>>) -> ProgressIter<Self> {
+        ProgressIter { inner: self, bar }
+    }
+}
+
+impl<I: Iterator> ProgressIterator for I {}
+
+// Apply `f` to every item on `workers` threads (0 = one per CPU), with one bar
+// tracking the items done across all of them. Results keep the input order.
+pub fn parallel_map<T, R, F>(items: Vec<T>, workers: usize, f: F) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+    F: Fn(T) -> R + Sync,
+{
+    let bar = ProgressBar::new(items.len() as u64);
+    parallel_map_with(&bar, items, workers, f)
+}
+
+// `parallel_map` driving an existing bar
+pub fn parallel_map_with<T, R, F>(bar: &Arc<Mutex<ProgressBar>>, items: Vec<T>, workers: usize, f: F) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+    F: Fn(T) -> R + Sync,
+{
+    let len = items.len();
+    let workers = match workers {
+        0 => thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    }
+    .min(len.max(1));
+
+    let queue = Mutex::new(items.into_iter().enumerate());
+    let results: Mutex<Vec<Option<R>>> = Mutex::new((0..len).map(|_| None).collect());
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                loop {
+                    // Take the next item without holding the queue while working on it
+                    let next = queue.lock().ok().and_then(|mut queue| queue.next());
+                    let Some((index, item)) = next else {
+                        break;
+                    };
+                    let result = f(item);
+                    if let Ok(mut results) = results.lock() {
+                        results[index] = Some(result);
+                    }
+                    if let Ok(mut bar) = bar.lock() {
+                        bar.increment(1);
+                    }
+                }
+            });
+        }
+    });
+
+    if let Ok(mut bar) = bar.lock() {
+        if !bar.is_finished() {
+            bar.finish();
+        }
+    }
+
+    results
+        .into_inner()
+        .unwrap_or_default()
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::{ProgressEventKind, ProgressState, ProgressTarget};
+    use std::time::Duration;
+
+    fn hidden_bar(total: u64) -> Arc<Mutex<ProgressBar>> {
+        let bar = ProgressBar::new(total);
+        bar.lock().unwrap().set_draw_target(ProgressTarget::Hidden);
+        bar
+    }
+
+    #[test]
+    fn parallel_results_keep_the_input_order() {
+        let bar = hidden_bar(50);
+        // Later items finish first, so completion order is the reverse of input order
+        let results = parallel_map_with(&bar, (0..50u64).collect(), 4, |n| {
+            thread::sleep(Duration::from_micros(50 - n));
+            n * 2
+        });
+        assert_eq!(results, (0..50).map(|n| n * 2).collect::<Vec<_>>());
+
+        let bar = bar.lock().unwrap();
+        assert_eq!(bar.current, 50);
+        assert_eq!(bar.state(), ProgressState::Finished);
+    }
+
+    #[test]
+    fn empty_input_finishes_the_bar() {
+        let bar = hidden_bar(0);
+        let results: Vec<u32> = parallel_map_with(&bar, Vec::<u32>::new(), 4, |n| n);
+        assert!(results.is_empty());
+        assert!(bar.lock().unwrap().is_finished());
+    }
+
+    #[test]
+    fn zero_workers_uses_the_available_cpus() {
+        let bar = hidden_bar(10);
+        let results = parallel_map_with(&bar, (0..10).collect(), 0, |n: i32| n - 1);
+        assert_eq!(results, (-1..9).collect::<Vec<_>>());
+        assert_eq!(bar.lock().unwrap().current, 10);
+    }
+
+    #[test]
+    fn exhausting_the_iterator_finishes_the_bar() {
+        let bar = hidden_bar(3);
+        let items: Vec<_> = (0..3).progress_with(bar.clone()).collect();
+        assert_eq!(items, [0, 1, 2]);
+        assert_eq!(bar.lock().unwrap().state(), ProgressState::Finished);
+    }
+
+    #[test]
+    fn dropping_the_iterator_early_abandons_the_bar() {
+        let kinds = Arc::new(Mutex::new(Vec::new()));
+        let mut iter = (0..10).progress();
+        {
+            let seen = kinds.clone();
+            iter.bar().lock().unwrap().set_draw_callback(move |event| seen.lock().unwrap().push(event.event));
+        }
+        assert_eq!(iter.next(), Some(0));
+        assert_eq!(iter.bar().lock().unwrap().current, 1);
+
+        // The iterator held the only handle to the bar
+        drop(iter);
+        assert_eq!(kinds.lock().unwrap().last(), Some(&ProgressEventKind::Abandoned));
+    }
+}
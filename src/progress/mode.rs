@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::color::{self, Stream};
+
+// How progress bars are shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProgressMode {
+    // Live bars on a terminal, plain lines otherwise (pipes, CI logs, TERM=dumb)
+    #[default]
+    Auto,
+    // Bars redrawn in place
+    Live,
+    // A plain status line every `plain_interval`, plus the final state
+    Plain,
+    Hidden,
+}
+
+impl ProgressMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProgressMode::Auto => "auto",
+            ProgressMode::Live => "live",
+            ProgressMode::Plain => "plain",
+            ProgressMode::Hidden => "hidden",
+        }
+    }
+
+    // The mode actually used for output going to `stream`; never `Auto`
+    pub fn resolve(&self, stream: Stream) -> ProgressMode {
+        match self {
+            ProgressMode::Auto if color::is_interactive(stream) => ProgressMode::Live,
+            ProgressMode::Auto => ProgressMode::Plain,
+            mode => *mode,
+        }
+    }
+}
+
+impl FromStr for ProgressMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(ProgressMode::Auto),
+            "live" => Ok(ProgressMode::Live),
+            "plain" => Ok(ProgressMode::Plain),
+            "hidden" => Ok(ProgressMode::Hidden),
+            other => Err(format!("不明な進捗表示モードです: {}", other)),
+        }
+    }
+}
+
+pub const DEFAULT_PLAIN_INTERVAL: Duration = Duration::from_secs(10);
+
+static MODE: AtomicU8 = AtomicU8::new(0);
+static PLAIN_INTERVAL_MS: AtomicU64 = AtomicU64::new(DEFAULT_PLAIN_INTERVAL.as_millis() as u64);
+
+pub fn set_progress_mode(mode: ProgressMode) {
+    let value = match mode {
+        ProgressMode::Auto => 0,
+        ProgressMode::Live => 1,
+        ProgressMode::Plain => 2,
+        ProgressMode::Hidden => 3,
+    };
+    MODE.store(value, Ordering::Relaxed);
+}
+
+pub fn progress_mode() -> ProgressMode {
+    match MODE.load(Ordering::Relaxed) {
+        1 => ProgressMode::Live,
+        2 => ProgressMode::Plain,
+        3 => ProgressMode::Hidden,
+        _ => ProgressMode::Auto,
+    }
+}
+
+// Time between status lines in plain mode
+pub fn set_plain_interval(interval: Duration) {
+    PLAIN_INTERVAL_MS.store(interval.as_millis() as u64, Ordering::Relaxed);
+}
+
+pub fn plain_interval() -> Duration {
+    Duration::from_millis(PLAIN_INTERVAL_MS.load(Ordering::Relaxed))
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use super::{ProgressBar, register_progress_bar};
+use crate::color::Stream;
+use crate::term;
+
+struct Entry {
+    bar: u64,
+    // Usually one line; a bar with sub-tasks adds the active one below it
+    lines: Vec<String>,
+    finished: bool,
+}
+
+pub(super) struct MultiState {
+    owner: u64,
+    keep_finished: AtomicBool,
+    // Draw on stdout instead of stderr
+    to_stdout: AtomicBool,
+    entries: Mutex<Vec<Entry>>,
+}
+
+impl MultiState {
+    // Called by a member bar with its freshly rendered lines
+    pub(super) fn update(&self, bar: u64, lines: Vec<String>, finished: bool) {
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+
+        match entries.iter_mut().find(|entry| entry.bar == bar) {
+            Some(entry) => {
+                entry.lines = lines;
+                entry.finished = finished;
+            }
+            None => entries.push(Entry { bar, lines, finished }),
+        }
+
+        let lines = self.visible_lines(&entries);
+        if entries.iter().all(|entry| entry.finished) {
+            // Everything is done: leave the block in the scrollback and start over
+            entries.clear();
+            term::finish_live(self.owner, self.stream(), lines);
+        } else {
+            term::set_live(self.owner, self.stream(), lines);
+        }
+    }
+
+    // Stream the whole block is drawn on, whatever its bars' own targets
+    pub(super) fn stream(&self) -> Stream {
+        if self.to_stdout.load(Ordering::Relaxed) { Stream::Stdout } else { Stream::Stderr }
+    }
+
+    fn visible_lines(&self, entries: &[Entry]) -> Vec<String> {
+        let keep_finished = self.keep_finished.load(Ordering::Relaxed);
+        entries
+            .iter()
+            .filter(|entry| keep_finished || !entry.finished)
+            .flat_map(|entry| entry.lines.iter().cloned())
+            .collect()
+    }
+}
+
+// Draws several progress bars together as one block of terminal lines.
+//
+// Clones share the same block, so a MultiProgress can be handed to worker threads.
+#[derive(Clone)]
+pub struct MultiProgress {
+    state: Arc<MultiState>,
+}
+
+impl MultiProgress {
+    pub fn new() -> Self {
+        MultiProgress {
+            state: Arc::new(MultiState {
+                owner: term::next_owner_id(),
+                keep_finished: AtomicBool::new(true),
+                to_stdout: AtomicBool::new(false),
+                entries: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    // Whether finished bars stay in the block (default) or disappear from it
+    pub fn with_keep_finished(self, keep_finished: bool) -> Self {
+        self.state.keep_finished.store(keep_finished, Ordering::Relaxed);
+        self
+    }
+
+    // Stream to draw the block on, stderr by default
+    pub fn with_stream(self, stream: Stream) -> Self {
+        self.state.to_stdout.store(stream == Stream::Stdout, Ordering::Relaxed);
+        self
+    }
+
+    // Create a new bar drawn as part of this block, below the existing ones
+    pub fn add(&self, total: u64) -> Arc<Mutex<ProgressBar>> {
+        let bar = ProgressBar::new(total);
+        self.attach(&bar);
+        bar
+    }
+
+    // Move an existing bar into this block
+    pub fn attach(&self, bar: &Arc<Mutex<ProgressBar>>) {
+        let Ok(mut bar) = bar.lock() else {
+            return;
+        };
+
+        if bar.group.is_none() {
+            term::remove_live(bar.owner);
+        }
+        if let Ok(mut entries) = self.state.entries.lock() {
+            if !entries.iter().any(|entry| entry.bar == bar.owner) {
+                entries.push(Entry { bar: bar.owner, lines: Vec::new(), finished: false });
+            }
+        }
+        bar.group = Some(self.state.clone());
+    }
+
+    // Leave the current lines in the scrollback, whether or not every bar is finished
+    pub fn finish(&self) {
+        if let Ok(mut entries) = self.state.entries.lock() {
+            let lines = self.state.visible_lines(&entries);
+            entries.clear();
+            term::finish_live(self.state.owner, self.state.stream(), lines);
+        }
+    }
+
+    // Remove the block from the screen without leaving anything behind
+    pub fn clear(&self) {
+        if let Ok(mut entries) = self.state.entries.lock() {
+            entries.clear();
+            term::remove_live(self.state.owner);
+        }
+    }
+}
+
+impl Default for MultiProgress {
+    fn default() -> Self {
+        MultiProgress::new()
+    }
+}
+
+static MULTI_PROGRESS: once_cell::sync::Lazy<Mutex<HashMap<usize, MultiProgress>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
+static NEXT_MULTI_ID: AtomicUsize = AtomicUsize::new(0);
+
+pub fn create_multi_progress(keep_finished: bool) -> usize {
+    let id = NEXT_MULTI_ID.fetch_add(1, Ordering::SeqCst);
+    if let Ok(mut multis) = MULTI_PROGRESS.lock() {
+        multis.insert(id, MultiProgress::new().with_keep_finished(keep_finished));
+    }
+    id
+}
+
+// Create a bar inside the multi-progress `multi_id`; returns its progress bar id
+pub fn add_to_multi_progress(multi_id: usize, total: u64) -> Option<usize> {
+    let multi = MULTI_PROGRESS.lock().ok()?.get(&multi_id)?.clone();
+    Some(register_progress_bar(multi.add(total)))
+}
+
+pub fn finish_multi_progress(multi_id: usize) -> bool {
+    let multi = MULTI_PROGRESS.lock().ok().and_then(|mut multis| multis.remove(&multi_id));
+    match multi {
+        Some(multi) => {
+            multi.finish();
+            true
+        }
+        None => false,
+    }
+}
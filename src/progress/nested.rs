@@ -0,0 +1,189 @@
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use super::{ProgressBar, ProgressStyle};
+
+// Position range of a parent bar; its position is the weighted completion of its
+// children in thousandths, which is also what its events report as pos and total
+pub const PARENT_SCALE: u64 = 1000;
+pub const PARENT_TEMPLATE: &str = "{icon} {prefix}{percent}% [{bar}] [{elapsed}, ETA: {eta}] {msg}";
+// Neither template shows {pos}/{len} or {rate}, which would expose the scale
+pub const PARENT_PLAIN_TEMPLATE: &str = "{prefix}{percent}% ETA {eta} {msg}";
+// Child lines are drawn indented under the parent
+pub(super) const CHILD_INDENT: &str = "  ";
+
+struct Child {
+    owner: u64,
+    weight: f64,
+    fraction: f64,
+    line: Option<String>,
+    done: bool,
+}
+
+// Sub-tasks of a parent bar
+#[derive(Default)]
+pub(super) struct Children {
+    children: Vec<Child>,
+    // Child that changed most recently and is still running
+    active: Option<u64>,
+}
+
+impl Children {
+    fn fraction(&self) -> f64 {
+        let total_weight: f64 = self.children.iter().map(|child| child.weight).sum();
+        if total_weight <= 0.0 {
+            return 0.0;
+        }
+        self.children.iter().map(|child| child.weight * child.fraction).sum::<f64>() / total_weight
+    }
+
+    pub(super) fn active_line(&self) -> Option<&str> {
+        let active = self.active?;
+        self.children.iter().find(|child| child.owner == active)?.line.as_deref()
+    }
+}
+
+impl ProgressBar {
+    // An overall bar made of weighted sub-tasks added with `add_child`. It shows
+    // its own progress plus the line of the sub-task currently running, and is
+    // finished by the caller like any other bar.
+    pub fn new_parent() -> Arc<Mutex<Self>> {
+        let parent = ProgressBar::new(PARENT_SCALE);
+        if let Ok(mut bar) = parent.lock() {
+            bar.style = ProgressStyle::new()
+                .with_template(PARENT_TEMPLATE)
+                .and_then(|style| style.with_plain_template(PARENT_PLAIN_TEMPLATE))
+                .expect("parent progress templates are valid");
+            bar.children = Some(Children::default());
+        }
+        parent
+    }
+
+    // A new sub-task of `parent` counting to `total`. `weight` is its share of
+    // the overall progress relative to the other children, e.g. 60, 30 and 10.
+    pub fn add_child(parent: &Arc<Mutex<Self>>, weight: f64, total: u64) -> Arc<Mutex<Self>> {
+        let child = ProgressBar::new(total);
+        ProgressBar::attach_child(parent, &child, weight);
+        child
+    }
+
+    // Make an existing bar a sub-task of `parent`; a spinner counts as done only when finished
+    pub fn attach_child(parent: &Arc<Mutex<Self>>, child: &Arc<Mutex<Self>>, weight: f64) {
+        // Child before parent, the order a child's redraw takes them in
+        let (Ok(mut child_bar), Ok(mut parent_bar)) = (child.lock(), parent.lock()) else {
+            return;
+        };
+
+        child_bar.clear_live();
+
+        let children = parent_bar.children.get_or_insert_with(Children::default);
+        if !children.children.iter().any(|c| c.owner == child_bar.owner) {
+            children.children.push(Child {
+                owner: child_bar.owner,
+                weight: weight.max(0.0),
+                fraction: child_bar.fraction(),
+                line: None,
+                done: child_bar.state.is_done(),
+            });
+        }
+        child_bar.parent = Some(parent.clone());
+        parent_bar.sync_children();
+    }
+
+    // Completion of this bar between 0 and 1
+    pub(super) fn fraction(&self) -> f64 {
+        match self.total {
+            Some(0) => 1.0,
+            Some(total) => self.current.min(total) as f64 / total as f64,
+            None if self.state.is_done() => 1.0,
+            None => 0.0,
+        }
+    }
+
+    // Called by a child, with the child locked, after it changed
+    pub(super) fn child_changed(&mut self, owner: u64, fraction: f64, line: String, done: bool) {
+        let Some(children) = self.children.as_mut() else {
+            return;
+        };
+        if let Some(child) = children.children.iter_mut().find(|child| child.owner == owner) {
+            child.fraction = fraction;
+            child.line = Some(line);
+            child.done = done;
+        }
+
+        if !done {
+            children.active = Some(owner);
+        } else if children.active == Some(owner) {
+            children.active = None;
+        }
+
+        if self.state.is_done() {
+            return;
+        }
+        self.sync_children();
+        // A child finishing is always shown, like the final state of any bar
+        if done {
+            self.draw(Instant::now());
+        }
+    }
+
+    fn sync_children(&mut self) {
+        let Some(children) = &self.children else {
+            return;
+        };
+        let current = (children.fraction() * PARENT_SCALE as f64).round() as u64;
+        self.update(current);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::ProgressTarget;
+    use std::io::{self, Write};
+
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn children_are_weighted() {
+        let parent = ProgressBar::new_parent();
+        let big = ProgressBar::add_child(&parent, 3.0, 10);
+        let small = ProgressBar::add_child(&parent, 1.0, 4);
+        parent.lock().unwrap().set_draw_target(ProgressTarget::Hidden);
+
+        // Finishing is always drawn, so it reaches the parent regardless of the draw rate
+        big.lock().unwrap().finish();
+        assert_eq!(parent.lock().unwrap().current, 750);
+        small.lock().unwrap().finish();
+        assert_eq!(parent.lock().unwrap().current, PARENT_SCALE);
+    }
+
+    #[test]
+    fn plain_parent_lines_hide_the_internal_scale() {
+        let output = Output::default();
+        let parent = ProgressBar::new_parent();
+        {
+            let mut bar = parent.lock().unwrap();
+            bar.set_draw_target(ProgressTarget::writer(output.clone()));
+            bar.update(420);
+            bar.finish();
+        }
+
+        let written = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        assert!(written.contains("100%"));
+        assert!(!written.contains("1000"));
+        assert!(!written.contains("420"));
+    }
+}
@@ -0,0 +1,498 @@
+use colored::*;
+use std::time::Duration;
+use unicode_width::UnicodeWidthStr;
+
+use super::ProgressState;
+use crate::color::paint_if;
+use crate::errors::{CliError, config_error};
+use crate::placeholder::{Token, tokenize};
+
+pub const DEFAULT_TEMPLATE: &str = "{icon} {percent}% [{bar}] {pos}/{len} [{elapsed}, {rate}/s, ETA: {eta}] {msg}";
+pub const DEFAULT_SPINNER_TEMPLATE: &str = "{spinner} {prefix}{msg} [{pos}, {elapsed}]";
+pub const BYTES_TEMPLATE: &str = "{icon} {percent}% [{bar}] {bytes}/{total_bytes} [{elapsed}, {bytes_per_sec}, ETA: {eta}] {msg}";
+// Status lines printed instead of a live bar when output is not a terminal
+pub const PLAIN_TEMPLATE: &str = "{prefix}{percent}% {pos}/{len} ETA {eta} {msg}";
+pub const PLAIN_SPINNER_TEMPLATE: &str = "{prefix}{pos} {elapsed} {msg}";
+const PLAIN_LINE_WIDTH: usize = 80;
+pub const DEFAULT_CHARS: &str = "=> ";
+// Full block, seven partial blocks from fullest to thinnest, then empty
+pub const BLOCK_CHARS: &str = "█▉▊▋▌▍▎▏ ";
+const DEFAULT_BAR_WIDTH: usize = 30;
+const MIN_BAR_WIDTH: usize = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Bar(Option<usize>),
+    Spinner,
+    Icon,
+    Percent,
+    Pos,
+    Len,
+    Bytes,
+    TotalBytes,
+    Rate,
+    BytesPerSec,
+    Eta,
+    Elapsed,
+    Message,
+    Prefix,
+}
+
+// Snapshot of a bar handed to the style for rendering
+pub(super) struct RenderState<'a> {
+    pub pos: u64,
+    // None while the total is unknown
+    pub len: Option<u64>,
+    pub elapsed: Duration,
+    pub rate: f64,
+    pub eta: Option<Duration>,
+    pub message: &'a str,
+    pub prefix: &'a str,
+    pub state: ProgressState,
+}
+
+// Built-in spinner animations
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpinnerFrames {
+    Dots,
+    Line,
+    Circle,
+    Bounce,
+    Arrow,
+}
+
+impl SpinnerFrames {
+    pub fn frames(&self) -> &'static [&'static str] {
+        match self {
+            SpinnerFrames::Dots => &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"],
+            SpinnerFrames::Line => &["-", "\\", "|", "/"],
+            SpinnerFrames::Circle => &["◐", "◓", "◑", "◒"],
+            SpinnerFrames::Bounce => &["⠁", "⠂", "⠄", "⠂"],
+            SpinnerFrames::Arrow => &["←", "↖", "↑", "↗", "→", "↘", "↓", "↙"],
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "dots" => Some(SpinnerFrames::Dots),
+            "line" => Some(SpinnerFrames::Line),
+            "circle" => Some(SpinnerFrames::Circle),
+            "bounce" => Some(SpinnerFrames::Bounce),
+            "arrow" => Some(SpinnerFrames::Arrow),
+            _ => None,
+        }
+    }
+}
+
+// Layout and look of a progress bar.
+//
+// Template placeholders: `{bar}` (or `{bar:<width>}`), `{spinner}`, `{icon}`, `{percent}`,
+// `{pos}`, `{len}`, `{rate}`, `{eta}`, `{elapsed}`, `{msg}` and `{prefix}`, plus `{bytes}`,
+// `{total_bytes}` and `{bytes_per_sec}` for byte counts. Use `{{` and `}}` for literal
+// braces. The spinner template is used while the total is unknown.
+#[derive(Debug, Clone)]
+pub struct ProgressStyle {
+    segments: Vec<Segment>,
+    spinner_segments: Vec<Segment>,
+    plain_segments: Vec<Segment>,
+    plain_spinner_segments: Vec<Segment>,
+    frames: Vec<String>,
+    frame_interval: Duration,
+    chars: Vec<char>,
+    width: Option<usize>,
+    bar_color: Option<Color>,
+}
+
+impl Default for ProgressStyle {
+    fn default() -> Self {
+        ProgressStyle {
+            segments: parse_template(DEFAULT_TEMPLATE).expect("default progress template is valid"),
+            spinner_segments: parse_template(DEFAULT_SPINNER_TEMPLATE).expect("default spinner template is valid"),
+            plain_segments: parse_template(PLAIN_TEMPLATE).expect("plain progress template is valid"),
+            plain_spinner_segments: parse_template(PLAIN_SPINNER_TEMPLATE).expect("plain spinner template is valid"),
+            frames: SpinnerFrames::Dots.frames().iter().map(|frame| frame.to_string()).collect(),
+            frame_interval: Duration::from_millis(80),
+            chars: DEFAULT_CHARS.chars().collect(),
+            width: Some(DEFAULT_BAR_WIDTH),
+            bar_color: None,
+        }
+    }
+}
+
+impl ProgressStyle {
+    pub fn new() -> Self {
+        ProgressStyle::default()
+    }
+
+    // Positions as KiB/MiB/GiB and throughput per second, for downloads and copies
+    pub fn bytes() -> Self {
+        ProgressStyle::default()
+            .with_template(BYTES_TEMPLATE)
+            .expect("bytes progress template is valid")
+            .with_spinner_template("{spinner} {prefix}{msg} [{bytes}, {bytes_per_sec}, {elapsed}]")
+            .expect("bytes spinner template is valid")
+            .with_plain_template("{prefix}{percent}% {bytes}/{total_bytes} {bytes_per_sec} ETA {eta} {msg}")
+            .expect("bytes plain template is valid")
+            .with_plain_spinner_template("{prefix}{bytes} {bytes_per_sec} {elapsed} {msg}")
+            .expect("bytes plain spinner template is valid")
+    }
+
+    pub fn with_template(mut self, template: &str) -> Result<Self, CliError> {
+        self.segments = parse_template(template)?;
+        Ok(self)
+    }
+
+    // Layout used while the total is unknown
+    pub fn with_spinner_template(mut self, template: &str) -> Result<Self, CliError> {
+        self.spinner_segments = parse_template(template)?;
+        Ok(self)
+    }
+
+    // Status line printed periodically when output is not a terminal
+    pub fn with_plain_template(mut self, template: &str) -> Result<Self, CliError> {
+        self.plain_segments = parse_template(template)?;
+        Ok(self)
+    }
+
+    pub fn with_plain_spinner_template(mut self, template: &str) -> Result<Self, CliError> {
+        self.plain_spinner_segments = parse_template(template)?;
+        Ok(self)
+    }
+
+    pub fn with_spinner(mut self, frames: SpinnerFrames) -> Self {
+        self.frames = frames.frames().iter().map(|frame| frame.to_string()).collect();
+        self
+    }
+
+    // Custom animation, one frame per entry
+    pub fn with_spinner_frames(mut self, frames: &[&str]) -> Result<Self, CliError> {
+        if frames.is_empty() {
+            return Err(config_error("スピナーのフレームを1つ以上指定してください"));
+        }
+        self.frames = frames.iter().map(|frame| frame.to_string()).collect();
+        Ok(self)
+    }
+
+    // How long each spinner frame is shown
+    pub fn with_frame_interval(mut self, interval: Duration) -> Self {
+        self.frame_interval = interval.max(Duration::from_millis(1));
+        self
+    }
+
+    // Characters used to draw the bar: the first is a filled cell, the last an
+    // empty one, and any in between are partially filled cells from fullest to
+    // thinnest, e.g. "=> " or `BLOCK_CHARS`
+    pub fn with_chars(mut self, chars: &str) -> Result<Self, CliError> {
+        let chars: Vec<char> = chars.chars().collect();
+        if chars.len() < 2 {
+            return Err(config_error("バーの文字は2文字以上指定してください"));
+        }
+        self.chars = chars;
+        Ok(self)
+    }
+
+    // Bar width in cells; `None` fills the rest of the terminal line.
+    // The bar always shrinks to keep the line within the terminal.
+    pub fn with_width(mut self, width: Option<usize>) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn with_bar_color(mut self, color: Option<Color>) -> Self {
+        self.bar_color = color;
+        self
+    }
+
+    pub(super) fn render(&self, state: &RenderState, term_width: usize, color: bool) -> String {
+        let segments = if state.len.is_some() { &self.segments } else { &self.spinner_segments };
+        self.render_segments(segments, state, term_width, color)
+    }
+
+    pub(super) fn render_plain(&self, state: &RenderState, color: bool) -> String {
+        let segments = if state.len.is_some() { &self.plain_segments } else { &self.plain_spinner_segments };
+        let line = self.render_segments(segments, state, PLAIN_LINE_WIDTH, color);
+        line.trim_end().to_string()
+    }
+
+    fn render_segments(&self, segments: &[Segment], state: &RenderState, term_width: usize, color: bool) -> String {
+        let percent = match state.len {
+            Some(0) => 100,
+            Some(len) => (state.pos.min(len) as f64 / len as f64 * 100.0) as u64,
+            None => 0,
+        };
+
+        // Width of everything but the bar decides how much room the bar gets
+        let mut fixed_width = 0;
+        let mut bar_count = 0;
+        let mut texts = Vec::with_capacity(segments.len());
+        for segment in segments {
+            let text = match segment {
+                Segment::Literal(text) => text.clone(),
+                Segment::Bar(_) => {
+                    bar_count += 1;
+                    String::new()
+                }
+                Segment::Spinner if state.state == ProgressState::Running => self.frame_at(state.elapsed).to_string(),
+                Segment::Icon | Segment::Spinner => state_icon(state.state).to_string(),
+                Segment::Percent => percent.to_string(),
+                Segment::Pos => state.pos.to_string(),
+                Segment::Len => state.len.map(|len| len.to_string()).unwrap_or_else(|| "?".to_string()),
+                Segment::Bytes => format_bytes(state.pos),
+                Segment::TotalBytes => state.len.map(format_bytes).unwrap_or_else(|| "?".to_string()),
+                Segment::Rate => format!("{:.2}", state.rate),
+                Segment::BytesPerSec => format!("{}/s", format_bytes(state.rate as u64)),
+                Segment::Eta => match state.eta {
+                    Some(eta) => format_duration(eta),
+                    None if state.state == ProgressState::Finished || state.len.is_some_and(|len| state.pos >= len) => {
+                        format_duration(Duration::ZERO)
+                    }
+                    None => "--".to_string(),
+                },
+                Segment::Elapsed => format_duration(state.elapsed),
+                Segment::Message => state.message.to_string(),
+                Segment::Prefix => state.prefix.to_string(),
+            };
+            fixed_width += text.width();
+            texts.push(text);
+        }
+
+        let available = term_width.saturating_sub(fixed_width + 1) / bar_count.max(1);
+        let mut line = String::new();
+        for (segment, text) in segments.iter().zip(texts) {
+            match segment {
+                Segment::Bar(width) => {
+                    let wanted = width.or(self.width).unwrap_or(available);
+                    let width = wanted.min(available).max(MIN_BAR_WIDTH);
+                    line.push_str(&self.render_bar(state, width, color));
+                }
+                Segment::Icon | Segment::Spinner => {
+                    let styled = match state.state {
+                        ProgressState::Running => text.bright_blue(),
+                        ProgressState::Finished => text.green(),
+                        ProgressState::Abandoned => text.yellow(),
+                        ProgressState::Cancelled => text.red(),
+                    };
+                    line.push_str(&paint_if(styled, color));
+                }
+                _ => line.push_str(&text),
+            }
+        }
+        line
+    }
+
+    fn frame_at(&self, elapsed: Duration) -> &str {
+        let index = (elapsed.as_millis() / self.frame_interval.as_millis().max(1)) as usize;
+        &self.frames[index % self.frames.len()]
+    }
+
+    fn render_bar(&self, state: &RenderState, width: usize, color: bool) -> String {
+        let ratio = match state.len {
+            Some(0) | None => 1.0,
+            Some(len) => (state.pos.min(len) as f64 / len as f64).clamp(0.0, 1.0),
+        };
+
+        let full = self.chars[0];
+        let empty = self.chars[self.chars.len() - 1];
+        let partials = &self.chars[1..self.chars.len() - 1];
+
+        let cells = ratio * width as f64;
+        let filled = cells.floor() as usize;
+        let mut bar: String = std::iter::repeat_n(full, filled).collect();
+
+        if filled < width {
+            let head = match partials.len() {
+                0 => empty,
+                // A single middle char is a head that always marks the edge, as in "=> "
+                1 => partials[0],
+                n => {
+                    // Partials run from fullest to thinnest, splitting a cell into n + 1 levels
+                    let level = ((cells - filled as f64) * (n + 1) as f64).floor() as usize;
+                    if level == 0 { empty } else { partials[n - level.min(n)] }
+                }
+            };
+            bar.push(head);
+            bar.extend(std::iter::repeat_n(empty, width - filled - 1));
+        }
+
+        match self.bar_color {
+            Some(bar_color) => paint_if(bar.color(bar_color), color),
+            None => bar,
+        }
+    }
+}
+
+fn state_icon(state: ProgressState) -> &'static str {
+    match state {
+        ProgressState::Running => "•",
+        ProgressState::Finished => "✓",
+        ProgressState::Abandoned => "!",
+        ProgressState::Cancelled => "✗",
+    }
+}
+
+fn parse_template(template: &str) -> Result<Vec<Segment>, CliError> {
+    tokenize(template, "テンプレート")?
+        .into_iter()
+        .map(|token| match token {
+            Token::Literal(text) => Ok(Segment::Literal(text)),
+            Token::Placeholder(name) => parse_placeholder(&name),
+        })
+        .collect()
+}
+
+fn parse_placeholder(name: &str) -> Result<Segment, CliError> {
+    let (key, arg) = match name.split_once(':') {
+        Some((key, arg)) => (key.trim(), Some(arg.trim())),
+        None => (name.trim(), None),
+    };
+
+    match (key, arg) {
+        ("bar", None) => Ok(Segment::Bar(None)),
+        ("bar", Some(width)) => width
+            .parse()
+            .map(|width| Segment::Bar(Some(width)))
+            .map_err(|_| config_error(format!("バーの幅が不正です: {}", width))),
+        ("spinner", None) => Ok(Segment::Spinner),
+        ("icon", None) => Ok(Segment::Icon),
+        ("percent", None) => Ok(Segment::Percent),
+        ("pos", None) => Ok(Segment::Pos),
+        ("len", None) => Ok(Segment::Len),
+        ("bytes", None) => Ok(Segment::Bytes),
+        ("total_bytes", None) => Ok(Segment::TotalBytes),
+        ("rate", None) => Ok(Segment::Rate),
+        ("bytes_per_sec", None) => Ok(Segment::BytesPerSec),
+        ("eta", None) => Ok(Segment::Eta),
+        ("elapsed", None) => Ok(Segment::Elapsed),
+        ("msg", None) | ("message", None) => Ok(Segment::Message),
+        ("prefix", None) => Ok(Segment::Prefix),
+        _ => Err(config_error(format!("不明なテンプレートのプレースホルダーです: {{{}}}", name))),
+    }
+}
+
+// Byte count with binary units, e.g. "512 B" or "1.50 MiB"
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.2} {}", size, UNITS[unit])
+}
+
+// Compact duration such as "12s", "3m05s" or "1h02m"
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m{:02}s", secs / 60, secs % 60),
+        _ => format!("{}h{:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+// Color names accepted from C and config, e.g. "green" or "bright blue"
+pub fn parse_color(name: &str) -> Option<Color> {
+    name.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render_state(pos: u64, len: Option<u64>) -> RenderState<'static> {
+        RenderState {
+            pos,
+            len,
+            elapsed: Duration::ZERO,
+            rate: 0.0,
+            eta: None,
+            message: "",
+            prefix: "",
+            state: ProgressState::Running,
+        }
+    }
+
+    fn bar(style: &ProgressStyle, pos: u64, len: Option<u64>) -> String {
+        style.render_bar(&render_state(pos, len), 4, false)
+    }
+
+    #[test]
+    fn block_bar_splits_cells_into_eighths() {
+        let style = ProgressStyle::new().with_chars(BLOCK_CHARS).unwrap();
+        // 32 steps over 4 cells is 8 steps a cell, one per partial level
+        assert_eq!(bar(&style, 0, Some(32)), "    ");
+        assert_eq!(bar(&style, 8, Some(32)), "█   ");
+        assert_eq!(bar(&style, 9, Some(32)), "█▏  ");
+        assert_eq!(bar(&style, 12, Some(32)), "█▌  ");
+        assert_eq!(bar(&style, 15, Some(32)), "█▉  ");
+        assert_eq!(bar(&style, 31, Some(32)), "███▉");
+        assert_eq!(bar(&style, 32, Some(32)), "████");
+        assert_eq!(bar(&style, 99, Some(32)), "████");
+    }
+
+    #[test]
+    fn single_head_always_marks_the_edge() {
+        let style = ProgressStyle::new();
+        assert_eq!(bar(&style, 0, Some(4)), ">   ");
+        assert_eq!(bar(&style, 2, Some(4)), "==> ");
+        assert_eq!(bar(&style, 4, Some(4)), "====");
+        // Without any partial chars the edge is just empty
+        let style = ProgressStyle::new().with_chars("#.").unwrap();
+        assert_eq!(bar(&style, 1, Some(4)), "#...");
+        // Nothing to measure against draws a full bar
+        assert_eq!(bar(&style, 0, Some(0)), "####");
+        assert_eq!(bar(&style, 3, None), "####");
+    }
+
+    #[test]
+    fn parses_template_placeholders() {
+        assert_eq!(
+            parse_template("{{{bar:12}}} {pos}/{ len } {message}").unwrap(),
+            vec![
+                Segment::Literal("{".to_string()),
+                Segment::Bar(Some(12)),
+                Segment::Literal("} ".to_string()),
+                Segment::Pos,
+                Segment::Literal("/".to_string()),
+                Segment::Len,
+                Segment::Literal(" ".to_string()),
+                Segment::Message,
+            ]
+        );
+        assert!(parse_template("{bar:wide}").is_err());
+        assert!(parse_template("{pos:3}").is_err());
+        assert!(parse_template("{unknown}").is_err());
+        assert!(parse_template("{pos").is_err());
+    }
+
+    #[test]
+    fn renders_a_plain_template() {
+        let style = ProgressStyle::new().with_plain_template("{prefix}{pos}/{len} {percent}% {msg}").unwrap();
+        let state = RenderState { message: "copying", prefix: "a: ", ..render_state(5, Some(20)) };
+        assert_eq!(style.render_plain(&state, false), "a: 5/20 25% copying");
+    }
+
+    #[test]
+    fn format_duration_picks_two_units() {
+        assert_eq!(format_duration(Duration::ZERO), "0s");
+        assert_eq!(format_duration(Duration::from_millis(59_999)), "59s");
+        assert_eq!(format_duration(Duration::from_secs(185)), "3m05s");
+        assert_eq!(format_duration(Duration::from_secs(3720)), "1h02m");
+        assert_eq!(format_duration(Duration::from_secs(100 * 3600)), "100h00m");
+    }
+
+    #[test]
+    fn format_bytes_uses_binary_units() {
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(1023), "1023 B");
+        assert_eq!(format_bytes(1024), "1.00 KiB");
+        assert_eq!(format_bytes(1536), "1.50 KiB");
+        assert_eq!(format_bytes(5 * 1024 * 1024), "5.00 MiB");
+        assert_eq!(format_bytes(u64::MAX), "16384.00 PiB");
+    }
+}
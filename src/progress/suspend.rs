@@ -0,0 +1,83 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::term;
+
+// Time all bars spent suspended, so it can be left out of their elapsed time and ETA
+struct SuspendClock {
+    // Nesting depth of `suspend_all`
+    depth: usize,
+    since: Option<Instant>,
+    total: Duration,
+}
+
+static CLOCK: Mutex<SuspendClock> = Mutex::new(SuspendClock {
+    depth: 0,
+    since: None,
+    total: Duration::ZERO,
+});
+
+// Total time spent suspended up to `now`, including a suspension still going on
+pub(super) fn suspended_total(now: Instant) -> Duration {
+    match CLOCK.lock() {
+        Ok(clock) => clock.total + clock.since.map_or(Duration::ZERO, |since| now.saturating_duration_since(since)),
+        Err(_) => Duration::ZERO,
+    }
+}
+
+// Hides every progress bar and stops their clocks until dropped
+pub struct SuspendGuard {
+    _private: (),
+}
+
+// Take all progress bars off the screen and pause their timing, e.g. to ask
+// a question with `interactive::confirm`. They come back when the guard is dropped.
+pub fn suspend_all() -> SuspendGuard {
+    if let Ok(mut clock) = CLOCK.lock() {
+        clock.depth += 1;
+        if clock.depth == 1 {
+            clock.since = Some(Instant::now());
+        }
+    }
+    term::hide_live();
+    SuspendGuard { _private: () }
+}
+
+// Run `f` with all progress bars suspended
+pub fn suspend<R>(f: impl FnOnce() -> R) -> R {
+    let _guard = suspend_all();
+    f()
+}
+
+impl Drop for SuspendGuard {
+    fn drop(&mut self) {
+        if let Ok(mut clock) = CLOCK.lock() {
+            clock.depth = clock.depth.saturating_sub(1);
+            if clock.depth == 0 {
+                if let Some(since) = clock.since.take() {
+                    clock.total += since.elapsed();
+                }
+            }
+        }
+        term::show_live();
+    }
+}
+
+// C interface
+
+static C_GUARDS: Mutex<Vec<SuspendGuard>> = Mutex::new(Vec::new());
+
+// Hide all progress bars and pause their timing until cli_resume_all_progress
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_suspend_all_progress() {
+    let guard = suspend_all();
+    if let Ok(mut guards) = C_GUARDS.lock() {
+        guards.push(guard);
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn cli_resume_all_progress() {
+    let guard = C_GUARDS.lock().ok().and_then(|mut guards| guards.pop());
+    drop(guard);
+}
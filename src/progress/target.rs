@@ -0,0 +1,36 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use crate::color::Stream;
+
+// Where a progress bar is drawn
+#[derive(Clone, Default)]
+pub enum ProgressTarget {
+    Stdout,
+    // Keeps stdout free for a tool's actual output
+    #[default]
+    Stderr,
+    // Plain, uncolored status lines written to any writer, e.g. a log file
+    Writer(Arc<Mutex<dyn Write + Send>>),
+    Hidden,
+}
+
+impl ProgressTarget {
+    pub fn writer<W: Write + Send + 'static>(writer: W) -> Self {
+        ProgressTarget::Writer(Arc::new(Mutex::new(writer)))
+    }
+
+    // Whether lines drawn here carry color when the stream's own decision is
+    // `colorize`; files and pipes given as a writer never get escapes
+    pub(super) fn colors(&self, colorize: bool) -> bool {
+        colorize && !matches!(self, ProgressTarget::Writer(_))
+    }
+
+    // Terminal stream used for the width and color decisions
+    pub(super) fn stream(&self) -> Stream {
+        match self {
+            ProgressTarget::Stdout => Stream::Stdout,
+            _ => Stream::Stderr,
+        }
+    }
+}
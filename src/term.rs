@@ -0,0 +1,288 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::sync::{Mutex, PoisonError};
+use std::sync::atomic::{AtomicU64, Ordering};
+use terminal_size::{Width, terminal_size_of};
+use unicode_width::UnicodeWidthChar;
+
+use crate::color::Stream;
+
+// Terminal output shared by the logger and progress bars.
+//
+// Progress bars own "live" lines at the bottom of the screen that are redrawn
+// in place. Each owner (a standalone bar or a multi-progress group) has its own
+// lines on stdout or stderr; together the lines of a stream form its live block,
+// ordered by owner id. When both streams are the same terminal they share one
+// block. Anything else printed while a block is on screen clears it first and
+// draws it again afterwards, all under one lock so threads can't interleave.
+struct Live {
+    stream: Stream,
+    lines: Vec<String>,
+}
+
+struct TermState {
+    live: BTreeMap<u64, Live>,
+    // Lines of each block currently on screen, by `block_index`; the cursor sits at the end of the last one
+    drawn_lines: [usize; 2],
+    // While above zero the live blocks are kept off the screen
+    hidden: usize,
+    // Progress lines printed while hidden, written out by `show_live`
+    held: Vec<(Stream, String)>,
+}
+
+static TERM: Mutex<TermState> = Mutex::new(TermState {
+    live: BTreeMap::new(),
+    drawn_lines: [0, 0],
+    hidden: 0,
+    held: Vec::new(),
+});
+
+static NEXT_OWNER: AtomicU64 = AtomicU64::new(1);
+
+// Key for a new set of live lines; later owners are drawn below earlier ones
+pub(crate) fn next_owner_id() -> u64 {
+    NEXT_OWNER.fetch_add(1, Ordering::Relaxed)
+}
+
+// Locked output streams, so a redraw can't interleave with other writes
+struct Outputs<'a> {
+    stdout: io::StdoutLock<'a>,
+    stderr: io::StderrLock<'a>,
+}
+
+impl Outputs<'_> {
+    fn get(&mut self, stream: Stream) -> &mut dyn Write {
+        match stream {
+            Stream::Stdout => &mut self.stdout,
+            Stream::Stderr => &mut self.stderr,
+        }
+    }
+}
+
+// Block that live lines for `stream` are drawn in
+fn block_of(stream: Stream, shared: bool) -> Stream {
+    if shared { Stream::Stderr } else { stream }
+}
+
+fn block_index(block: Stream) -> usize {
+    match block {
+        Stream::Stdout => 0,
+        Stream::Stderr => 1,
+    }
+}
+
+impl TermState {
+    fn clear_block(&mut self, block: Stream, out: &mut Outputs) {
+        let drawn = &mut self.drawn_lines[block_index(block)];
+        if *drawn == 0 {
+            return;
+        }
+        let out = out.get(block);
+        let _ = write!(out, "\r");
+        if *drawn > 1 {
+            let _ = write!(out, "\x1b[{}A", *drawn - 1);
+        }
+        let _ = write!(out, "\x1b[J");
+        let _ = out.flush();
+        *drawn = 0;
+    }
+
+    fn draw_block(&mut self, block: Stream, shared: bool, out: &mut Outputs) {
+        if self.hidden > 0 {
+            return;
+        }
+        // A wrapped line would throw off the cursor movement in clear_block
+        let width = terminal_width(block).saturating_sub(1);
+        let lines: Vec<(Stream, &String)> = self
+            .live
+            .values()
+            .filter(|live| block_of(live.stream, shared) == block)
+            .flat_map(|live| live.lines.iter().map(move |line| (live.stream, line)))
+            .collect();
+        // In a shared block each line still goes to its own stream; both end up on the same screen
+        for (i, (stream, line)) in lines.iter().enumerate() {
+            let out = out.get(*stream);
+            if i > 0 {
+                let _ = writeln!(out);
+            }
+            let _ = write!(out, "{}", truncate_to_width(line, width));
+            let _ = out.flush();
+        }
+        self.drawn_lines[block_index(block)] = lines.len();
+    }
+
+    // Progress output waits while hidden so it can't land on a prompt line
+    fn write_or_hold(&mut self, stream: Stream, line: &str, out: &mut Outputs) {
+        if self.hidden > 0 {
+            self.held.push((stream, line.to_string()));
+        } else {
+            let _ = writeln!(out.get(stream), "{}", line);
+        }
+    }
+}
+
+// Run `f` with the live blocks cleared, then draw them again
+fn with_block_cleared(f: impl FnOnce(&mut TermState, &mut Outputs)) {
+    // A panic mid-redraw leaves at worst a stale block, so keep printing rather than go silent
+    let mut state = TERM.lock().unwrap_or_else(PoisonError::into_inner);
+
+    let shared = Stream::Stdout.is_terminal() && Stream::Stderr.is_terminal();
+    let blocks: &[Stream] = if shared { &[Stream::Stderr] } else { &[Stream::Stdout, Stream::Stderr] };
+    let mut out = Outputs {
+        stdout: io::stdout().lock(),
+        stderr: io::stderr().lock(),
+    };
+    for &block in blocks {
+        state.clear_block(block, &mut out);
+    }
+    f(&mut state, &mut out);
+    for &block in blocks {
+        state.draw_block(block, shared, &mut out);
+    }
+    let _ = out.stdout.flush();
+    let _ = out.stderr.flush();
+}
+
+// Print a full line to stdout above the live blocks, also while they are hidden
+pub(crate) fn print_line(line: &str) {
+    with_block_cleared(|_, out| {
+        let _ = writeln!(out.get(Stream::Stdout), "{}", line);
+    });
+}
+
+// Print a progress line to `stream` above the live blocks, held back while they are hidden
+pub(crate) fn print_line_to(stream: Stream, line: &str) {
+    with_block_cleared(|state, out| state.write_or_hold(stream, line, out));
+}
+
+// Replace the live lines of `owner`, drawn on `stream`; no lines removes them
+pub(crate) fn set_live(owner: u64, stream: Stream, lines: Vec<String>) {
+    if lines.is_empty() {
+        return remove_live(owner);
+    }
+    with_block_cleared(|state, _| {
+        state.live.insert(owner, Live { stream, lines });
+    });
+}
+
+// Take the live lines of `owner` off the screen
+pub(crate) fn remove_live(owner: u64) {
+    with_block_cleared(|state, _| {
+        state.live.remove(&owner);
+    });
+}
+
+// Drop the live lines of `owner`, leaving `lines` in the scrollback of `stream` instead
+pub(crate) fn finish_live(owner: u64, stream: Stream, lines: Vec<String>) {
+    with_block_cleared(|state, out| {
+        state.live.remove(&owner);
+        for line in &lines {
+            state.write_or_hold(stream, line, out);
+        }
+    });
+}
+
+// Take the live block off the screen, e.g. while the user answers a prompt.
+// Calls nest; the block comes back with the matching `show_live`, along with
+// any progress lines printed in the meantime.
+pub(crate) fn hide_live() {
+    with_block_cleared(|state, _| state.hidden += 1);
+}
+
+pub(crate) fn show_live() {
+    with_block_cleared(|state, out| {
+        state.hidden = state.hidden.saturating_sub(1);
+        if state.hidden == 0 {
+            for (stream, line) in std::mem::take(&mut state.held) {
+                let _ = writeln!(out.get(stream), "{}", line);
+            }
+        }
+    });
+}
+
+// Columns of the terminal behind `stream`, falling back to $COLUMNS and then 80
+pub(crate) fn terminal_width(stream: Stream) -> usize {
+    let size = match stream {
+        Stream::Stdout => terminal_size_of(io::stdout()),
+        Stream::Stderr => terminal_size_of(io::stderr()),
+    };
+    match size {
+        Some((Width(width), _)) if width > 0 => width as usize,
+        _ => std::env::var("COLUMNS")
+            .ok()
+            .and_then(|columns| columns.parse().ok())
+            .filter(|&columns| columns > 0)
+            .unwrap_or(80),
+    }
+}
+
+// Cut `line` to `width` display columns, skipping over ANSI escape sequences
+pub(crate) fn truncate_to_width(line: &str, width: usize) -> std::borrow::Cow<'_, str> {
+    let mut used = 0;
+    let mut in_escape = false;
+    let mut saw_escape = false;
+
+    for (index, c) in line.char_indices() {
+        if in_escape {
+            if c.is_ascii_alphabetic() {
+                in_escape = false;
+            }
+            continue;
+        }
+        if c == '\x1b' {
+            in_escape = true;
+            saw_escape = true;
+            continue;
+        }
+        used += c.width().unwrap_or(0);
+        if used > width {
+            let mut cut = line[..index].to_string();
+            if saw_escape {
+                cut.push_str("\x1b[0m");
+            }
+            return cut.into();
+        }
+    }
+    line.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_lines_are_left_alone() {
+        assert!(matches!(truncate_to_width("hello", 5), std::borrow::Cow::Borrowed("hello")));
+        assert_eq!(truncate_to_width("", 0), "");
+    }
+
+    #[test]
+    fn cuts_at_the_display_width() {
+        assert_eq!(truncate_to_width("hello world", 5), "hello");
+        // Wide characters take two columns and are never split
+        assert_eq!(truncate_to_width("日本語テキスト", 6), "日本語");
+        assert_eq!(truncate_to_width("日本語テキスト", 5), "日本");
+    }
+
+    #[test]
+    fn escapes_take_no_room_and_are_reset_after_a_cut() {
+        let line = "\x1b[32mgreen\x1b[0m text";
+        assert_eq!(truncate_to_width(line, 10), line);
+        assert_eq!(truncate_to_width(line, 3), "\x1b[32mgre\x1b[0m");
+        assert_eq!(truncate_to_width("\x1b[1;31mab", 1), "\x1b[1;31ma\x1b[0m");
+    }
+
+    #[test]
+    fn printing_survives_a_poisoned_lock() {
+        let _ = std::thread::spawn(|| {
+            let _state = TERM.lock();
+            panic!("poison the terminal state");
+        })
+        .join();
+        assert!(TERM.is_poisoned());
+
+        let mut ran = false;
+        with_block_cleared(|_, _| ran = true);
+        assert!(ran);
+    }
+}